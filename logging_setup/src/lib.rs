@@ -0,0 +1,153 @@
+use clap::{Arg, ArgMatches};
+use std::io::IsTerminal;
+use std::path::PathBuf;
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer, Registry};
+
+const LOG_LEVEL: &str = "log_level";
+const LOG_CONSOLE: &str = "log_console";
+const LOG_JSON: &str = "log_json";
+
+/// How logs are filtered, formatted and where they're written.
+///
+/// The level is taken from the `--log-level` flag, falling back to `RUST_LOG`, then to `info`.
+#[derive(Debug, Clone, Default)]
+pub struct LoggingConfig {
+  /// An `EnvFilter` directive such as `debug` or `thread_archive_scraper=trace`.
+  pub level: Option<String>,
+  /// The console stream logs are written to, if any.
+  pub console: Option<ConsoleStream>,
+  /// Whether logs are written as JSON lines instead of plain text.
+  pub json: bool,
+  /// A daily rolling log file to write to.
+  pub log_file: Option<LogFile>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConsoleStream {
+  Stdout,
+  Stderr,
+}
+
+#[derive(Debug, Clone)]
+pub struct LogFile {
+  pub directory: PathBuf,
+  pub file_name_prefix: String,
+}
+
+type BoxedLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
+/// The logging flags every binary accepts, to be added to its `clap::Command`.
+pub fn args() -> Vec<Arg> {
+  vec![
+    Arg::new(LOG_LEVEL)
+      .long("log-level")
+      .action(clap::ArgAction::Set)
+      .help("Sets the log level or filter directive. Defaults to `RUST_LOG`, then `info`."),
+    Arg::new(LOG_CONSOLE)
+      .long("log-console")
+      .action(clap::ArgAction::SetTrue)
+      .help("Writes logs to the console."),
+    Arg::new(LOG_JSON)
+      .long("log-json")
+      .action(clap::ArgAction::SetTrue)
+      .help("Formats logs as JSON lines."),
+  ]
+}
+
+/// The logging flags for binaries that always log to the console, leaving out `--log-console`.
+pub fn console_args() -> Vec<Arg> {
+  args()
+    .into_iter()
+    .filter(|arg| arg.get_id() != LOG_CONSOLE)
+    .collect()
+}
+
+impl LoggingConfig {
+  /// Reads the flags added by [`args`] or [`console_args`], with `--log-console` logging to stderr.
+  pub fn from_matches(matches: &ArgMatches) -> Self {
+    let log_console = matches
+      .try_get_one::<bool>(LOG_CONSOLE)
+      .ok()
+      .flatten()
+      .is_some_and(|log_console| *log_console);
+
+    Self {
+      level: matches.get_one::<String>(LOG_LEVEL).cloned(),
+      console: log_console.then_some(ConsoleStream::Stderr),
+      json: matches.get_flag(LOG_JSON),
+      log_file: None,
+    }
+  }
+
+  pub fn with_console(mut self, stream: ConsoleStream) -> Self {
+    self.console = Some(stream);
+    self
+  }
+
+  pub fn with_log_file<P: Into<PathBuf>>(mut self, directory: P, file_name_prefix: &str) -> Self {
+    self.log_file = Some(LogFile {
+      directory: directory.into(),
+      file_name_prefix: file_name_prefix.to_string(),
+    });
+    self
+  }
+
+  /// Installs the global subscriber.
+  ///
+  /// # Errors
+  /// - The level isn't a valid filter directive.
+  /// - A global subscriber was already set.
+  pub fn init(self) -> anyhow::Result<()> {
+    let filter = match &self.level {
+      Some(level) => EnvFilter::builder().parse(level)?,
+      None => EnvFilter::builder()
+        .with_default_directive(LevelFilter::INFO.into())
+        .from_env()?,
+    };
+
+    let mut layers: Vec<BoxedLayer> = vec![];
+
+    if let Some(log_file) = &self.log_file {
+      let file = tracing_appender::rolling::daily(&log_file.directory, &log_file.file_name_prefix);
+
+      layers.push(self.format_layer(file, false));
+    }
+
+    match self.console {
+      Some(ConsoleStream::Stdout) => {
+        layers.push(self.format_layer(std::io::stdout, std::io::stdout().is_terminal()));
+      }
+      Some(ConsoleStream::Stderr) => {
+        layers.push(self.format_layer(std::io::stderr, std::io::stderr().is_terminal()));
+      }
+      None => {}
+    }
+
+    tracing_subscriber::registry()
+      .with(layers)
+      .with(filter)
+      .try_init()?;
+
+    Ok(())
+  }
+
+  fn format_layer<W>(&self, writer: W, ansi: bool) -> BoxedLayer
+  where
+    W: for<'writer> tracing_subscriber::fmt::MakeWriter<'writer> + Send + Sync + 'static,
+  {
+    // Closing spans logs how long they were busy and idle for.
+    let layer = tracing_subscriber::fmt::layer()
+      .with_writer(writer)
+      .with_span_events(FmtSpan::CLOSE);
+
+    if self.json {
+      layer.json().boxed()
+    } else {
+      layer.with_ansi(ansi).boxed()
+    }
+  }
+}
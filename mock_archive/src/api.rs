@@ -0,0 +1,89 @@
+use crate::archive::{Archive, Post, Thread};
+use base64::Engine;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// A thread as FoolFuuka's `/_/api/chan/thread/` returns it, keyed by the thread ID.
+pub type ThreadResponse = BTreeMap<String, ApiThread>;
+
+#[derive(Debug, Serialize)]
+pub struct ApiThread {
+  pub op: ApiPost,
+  pub posts: BTreeMap<String, ApiPost>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ApiPost {
+  pub num: String,
+  pub thread_num: String,
+  pub board: ApiBoard,
+  pub timestamp: i64,
+  pub name: String,
+  pub title: Option<String>,
+  pub comment: String,
+  pub media: Option<ApiMedia>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ApiBoard {
+  pub shortname: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ApiMedia {
+  pub media_filename: String,
+  pub media_link: String,
+  /// The base64 encoded MD5 of the file.
+  pub media_hash: String,
+  pub media_size: String,
+  pub media_w: String,
+  pub media_h: String,
+}
+
+pub fn thread_response(archive: &Archive, thread: &Thread, base_url: &str) -> ThreadResponse {
+  let op = api_post(archive, thread, &thread.posts[0], base_url, true);
+  let posts = thread.posts[1..]
+    .iter()
+    .map(|post| {
+      (
+        post.id.clone(),
+        api_post(archive, thread, post, base_url, false),
+      )
+    })
+    .collect();
+
+  BTreeMap::from([(thread.id.clone(), ApiThread { op, posts })])
+}
+
+fn api_post(
+  archive: &Archive,
+  thread: &Thread,
+  post: &Post,
+  base_url: &str,
+  is_op: bool,
+) -> ApiPost {
+  let comment = std::iter::once(post.text.clone())
+    .chain(post.hyperlinks.iter().cloned())
+    .collect::<Vec<String>>()
+    .join("\n");
+
+  ApiPost {
+    num: post.id.clone(),
+    thread_num: thread.id.clone(),
+    board: ApiBoard {
+      shortname: archive.settings.board.clone(),
+    },
+    timestamp: post.posted_at.timestamp(),
+    name: "Anonymous".to_string(),
+    title: is_op.then(|| archive.settings.subject.clone()),
+    comment,
+    media: post.media.as_ref().map(|media| ApiMedia {
+      media_filename: format!("{}.{}", media.original_name, media.extension),
+      media_link: format!("{base_url}/media/{}", media.file_name),
+      media_hash: base64::engine::general_purpose::STANDARD.encode(media.md5),
+      media_size: media.size.to_string(),
+      media_w: media.width.to_string(),
+      media_h: media.height.to_string(),
+    }),
+  }
+}
@@ -0,0 +1,223 @@
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use md5::{Digest, Md5};
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use std::collections::HashMap;
+
+const FIRST_THREAD_ID: u64 = 70_000_000;
+const MEDIA_CHANCE: f64 = 0.4;
+/// The chance media is a repost of something already posted, with the same MD5.
+const REPOST_CHANCE: f64 = 0.1;
+const HYPERLINK_CHANCE: f64 = 0.2;
+const MEDIA_SIZE_RANGE: std::ops::Range<usize> = 512..16_384;
+const MEDIA_EXTENSIONS: &[&str] = &["png", "jpg", "gif", "webm", "mp4"];
+/// A mix of hosts the scraper keeps, and ones on its default banned list.
+const HYPERLINK_HOSTS: &[&str] = &[
+  "https://pomf2.lain.la/f/",
+  "https://mega.nz/file/",
+  "https://files.catbox.moe/",
+  "https://www.youtube.com/watch?v=",
+  "https://x.com/status/",
+];
+
+/// What to generate an archive from. The same settings always generate the same archive.
+#[derive(Debug, Clone)]
+pub struct ArchiveSettings {
+  pub seed: u64,
+  pub board: String,
+  /// The subject every thread is posted under, and that the search pages search for.
+  pub subject: String,
+  pub thread_count: usize,
+  pub posts_per_thread: usize,
+  pub threads_per_page: usize,
+}
+
+/// A synthetic FoolFuuka board.
+#[derive(Debug, Clone)]
+pub struct Archive {
+  pub settings: ArchiveSettings,
+  pub threads: Vec<Thread>,
+  media_files: HashMap<String, Media>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Thread {
+  pub id: String,
+  /// The opening post comes first.
+  pub posts: Vec<Post>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Post {
+  pub id: String,
+  pub posted_at: DateTime<Utc>,
+  pub text: String,
+  pub hyperlinks: Vec<String>,
+  pub media: Option<Media>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Media {
+  /// The name the archive serves the file under, e.g. `1709294400001.png`.
+  pub file_name: String,
+  pub original_name: String,
+  pub extension: String,
+  pub size: usize,
+  pub width: u32,
+  pub height: u32,
+  pub md5: [u8; 16],
+  /// The bytes are regenerated from this whenever they're served, rather than being kept around.
+  bytes_seed: u64,
+}
+
+impl Archive {
+  pub fn generate(settings: ArchiveSettings) -> Self {
+    let mut rng = StdRng::seed_from_u64(settings.seed);
+    let mut media_files: HashMap<String, Media> = HashMap::new();
+    let mut posted_media: Vec<Media> = vec![];
+    let mut threads = vec![];
+    let mut next_post_id = FIRST_THREAD_ID;
+    let mut posted_at = Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap();
+
+    for _ in 0..settings.thread_count {
+      let mut posts = vec![];
+
+      for post_index in 0..settings.posts_per_thread.max(1) {
+        let id = next_post_id.to_string();
+        next_post_id += 1;
+        posted_at += Duration::seconds(rng.gen_range(5..600));
+
+        let media = if post_index == 0 || rng.gen_bool(MEDIA_CHANCE) {
+          let media = match posted_media.choose(&mut rng) {
+            Some(original) if rng.gen_bool(REPOST_CHANCE) => Media {
+              file_name: format!(
+                "{}{:03}.{}",
+                posted_at.timestamp(),
+                posted_media.len() % 1000,
+                original.extension
+              ),
+              ..original.clone()
+            },
+            _ => Media::generate(&mut rng, posted_at, posted_media.len()),
+          };
+
+          media_files.insert(media.file_name.clone(), media.clone());
+          posted_media.push(media.clone());
+
+          Some(media)
+        } else {
+          None
+        };
+
+        let hyperlinks = if rng.gen_bool(HYPERLINK_CHANCE) {
+          (0..rng.gen_range(1..=2))
+            .map(|_| {
+              let host = HYPERLINK_HOSTS.choose(&mut rng).unwrap();
+
+              format!("{host}{:08x}", rng.gen::<u32>())
+            })
+            .collect()
+        } else {
+          vec![]
+        };
+
+        posts.push(Post {
+          text: format!("Post number {id}."),
+          id,
+          posted_at,
+          hyperlinks,
+          media,
+        });
+      }
+
+      threads.push(Thread {
+        id: posts[0].id.clone(),
+        posts,
+      });
+    }
+
+    // Search results list the newest threads first.
+    threads.reverse();
+
+    Self {
+      settings,
+      threads,
+      media_files,
+    }
+  }
+
+  pub fn thread(&self, thread_id: &str) -> Option<&Thread> {
+    self.threads.iter().find(|thread| thread.id == thread_id)
+  }
+
+  pub fn media(&self, file_name: &str) -> Option<&Media> {
+    self.media_files.get(file_name)
+  }
+
+  /// The threads listed on a search page, starting from page 1.
+  pub fn search_page(&self, page_number: usize) -> &[Thread] {
+    let threads_per_page = self.settings.threads_per_page.max(1);
+    let start = page_number.saturating_sub(1) * threads_per_page;
+
+    if page_number == 0 || start >= self.threads.len() {
+      return &[];
+    }
+
+    &self.threads[start..(start + threads_per_page).min(self.threads.len())]
+  }
+
+  pub fn page_count(&self) -> usize {
+    self
+      .threads
+      .len()
+      .div_ceil(self.settings.threads_per_page.max(1))
+  }
+}
+
+impl Media {
+  fn generate(rng: &mut StdRng, posted_at: DateTime<Utc>, media_number: usize) -> Self {
+    let extension = MEDIA_EXTENSIONS.choose(rng).unwrap().to_string();
+    let size = rng.gen_range(MEDIA_SIZE_RANGE);
+    let bytes_seed = rng.gen();
+    let md5 = Md5::digest(generate_bytes(bytes_seed, size)).into();
+
+    Self {
+      file_name: format!(
+        "{}{:03}.{extension}",
+        posted_at.timestamp(),
+        media_number % 1000
+      ),
+      original_name: format!("image_{media_number}"),
+      extension,
+      size,
+      width: rng.gen_range(100..4000),
+      height: rng.gen_range(100..4000),
+      md5,
+      bytes_seed,
+    }
+  }
+
+  pub fn bytes(&self) -> Vec<u8> {
+    generate_bytes(self.bytes_seed, self.size)
+  }
+}
+
+fn generate_bytes(seed: u64, size: usize) -> Vec<u8> {
+  let mut bytes = vec![0; size];
+  StdRng::seed_from_u64(seed).fill_bytes(&mut bytes);
+
+  bytes
+}
+
+impl Default for ArchiveSettings {
+  fn default() -> Self {
+    Self {
+      seed: 0,
+      board: "vt".to_string(),
+      subject: "/shon/".to_string(),
+      thread_count: 20,
+      posts_per_thread: 50,
+      threads_per_page: 10,
+    }
+  }
+}
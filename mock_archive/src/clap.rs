@@ -0,0 +1,157 @@
+use clap::builder::PossibleValuesParser;
+use clap::Arg;
+use clap::Command;
+use logging_setup::{ConsoleStream, LoggingConfig};
+use mock_archive::{ArchiveSettings, Faults, MarkupVariant};
+use std::time::Duration;
+
+pub struct Args {
+  args: clap::ArgMatches,
+}
+
+impl Args {
+  const PORT: &'static str = "port";
+  const SEED: &'static str = "seed";
+  const BOARD: &'static str = "board";
+  const SUBJECT: &'static str = "subject";
+  const THREAD_COUNT: &'static str = "threads";
+  const POSTS_PER_THREAD: &'static str = "posts";
+  const THREADS_PER_PAGE: &'static str = "threads_per_page";
+  const RATE_LIMIT_CHANCE: &'static str = "rate_limit_chance";
+  const SLOW_CHANCE: &'static str = "slow_chance";
+  const SLOW_DELAY: &'static str = "slow_delay";
+  const TRUNCATE_CHANCE: &'static str = "truncate_chance";
+  const MARKUP: &'static str = "markup";
+
+  pub fn new() -> Self {
+    let args = Self::setup_args();
+
+    Self { args }
+  }
+
+  pub fn get_port(&self) -> u16 {
+    *self.args.get_one::<u16>(Self::PORT).unwrap()
+  }
+
+  pub fn get_archive_settings(&self) -> ArchiveSettings {
+    ArchiveSettings {
+      seed: *self.args.get_one::<u64>(Self::SEED).unwrap(),
+      board: self.args.get_one::<String>(Self::BOARD).unwrap().clone(),
+      subject: self.args.get_one::<String>(Self::SUBJECT).unwrap().clone(),
+      thread_count: *self.args.get_one::<usize>(Self::THREAD_COUNT).unwrap(),
+      posts_per_thread: *self.args.get_one::<usize>(Self::POSTS_PER_THREAD).unwrap(),
+      threads_per_page: *self.args.get_one::<usize>(Self::THREADS_PER_PAGE).unwrap(),
+    }
+  }
+
+  pub fn get_faults(&self) -> Faults {
+    let markup = self.args.get_one::<String>(Self::MARKUP).unwrap();
+
+    Faults {
+      rate_limit_chance: *self.args.get_one::<f64>(Self::RATE_LIMIT_CHANCE).unwrap(),
+      slow_chance: *self.args.get_one::<f64>(Self::SLOW_CHANCE).unwrap(),
+      slow_delay: Duration::from_millis(*self.args.get_one::<u64>(Self::SLOW_DELAY).unwrap()),
+      truncate_chance: *self.args.get_one::<f64>(Self::TRUNCATE_CHANCE).unwrap(),
+      markup: markup.parse().unwrap(),
+    }
+  }
+
+  pub fn get_logging_config(&self) -> LoggingConfig {
+    LoggingConfig::from_matches(&self.args).with_console(ConsoleStream::Stderr)
+  }
+
+  fn setup_args() -> clap::ArgMatches {
+    Command::new("Serves a synthetic FoolFuuka archive generated from a seed.")
+      .arg(
+        Arg::new(Self::PORT)
+          .long("port")
+          .value_parser(clap::value_parser!(u16))
+          .default_value("8080")
+          .help("The local port to serve on."),
+      )
+      .arg(
+        Arg::new(Self::SEED)
+          .short('s')
+          .long("seed")
+          .value_parser(clap::value_parser!(u64))
+          .default_value("0")
+          .help("The seed the archive and its faults are generated from."),
+      )
+      .arg(
+        Arg::new(Self::BOARD)
+          .long("board")
+          .default_value("vt")
+          .help("The board the threads are posted on."),
+      )
+      .arg(
+        Arg::new(Self::SUBJECT)
+          .long("subject")
+          .default_value("/shon/")
+          .help("The subject every thread is posted under."),
+      )
+      .arg(
+        Arg::new(Self::THREAD_COUNT)
+          .long("threads")
+          .value_parser(clap::value_parser!(usize))
+          .default_value("20")
+          .help("How many threads to generate."),
+      )
+      .arg(
+        Arg::new(Self::POSTS_PER_THREAD)
+          .long("posts")
+          .value_parser(clap::value_parser!(usize))
+          .default_value("50")
+          .help("How many posts each thread has, the opening post included."),
+      )
+      .arg(
+        Arg::new(Self::THREADS_PER_PAGE)
+          .long("threads-per-page")
+          .value_parser(clap::value_parser!(usize))
+          .default_value("10")
+          .help("How many threads are listed on each search page."),
+      )
+      .arg(
+        Arg::new(Self::RATE_LIMIT_CHANCE)
+          .long("rate-limit-chance")
+          .value_parser(clap::value_parser!(f64))
+          .default_value("0")
+          .help("The chance, from 0 to 1, that a request gets a 429."),
+      )
+      .arg(
+        Arg::new(Self::SLOW_CHANCE)
+          .long("slow-chance")
+          .value_parser(clap::value_parser!(f64))
+          .default_value("0")
+          .help("The chance, from 0 to 1, that a response is delayed."),
+      )
+      .arg(
+        Arg::new(Self::SLOW_DELAY)
+          .long("slow-delay-ms")
+          .value_parser(clap::value_parser!(u64))
+          .default_value("2000")
+          .help("How long delayed responses are held back for, in milliseconds."),
+      )
+      .arg(
+        Arg::new(Self::TRUNCATE_CHANCE)
+          .long("truncate-chance")
+          .value_parser(clap::value_parser!(f64))
+          .default_value("0")
+          .help("The chance, from 0 to 1, that a media file is cut off partway through."),
+      )
+      .arg(
+        Arg::new(Self::MARKUP)
+          .long("markup")
+          .value_parser(PossibleValuesParser::new(MarkupVariant::NAMES))
+          .default_value("standard")
+          .help("A variation on the archive's markup to serve."),
+      )
+      .args(logging_setup::console_args())
+      .get_matches()
+  }
+}
+
+impl Default for Args {
+  fn default() -> Self {
+    Self::new()
+  }
+}
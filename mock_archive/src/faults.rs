@@ -0,0 +1,104 @@
+use serde::Serialize;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Misbehaviour the server injects into its responses, each chance being between 0 and 1.
+#[derive(Debug, Clone, Default)]
+pub struct Faults {
+  /// The chance a request gets a `429 Too Many Requests`.
+  pub rate_limit_chance: f64,
+  /// The chance a response is held back by `slow_delay` before being sent.
+  pub slow_chance: f64,
+  pub slow_delay: Duration,
+  /// The chance a media file is cut off halfway through.
+  pub truncate_chance: f64,
+  pub markup: MarkupVariant,
+}
+
+/// Changes to the archive's markup, to check how parsing copes with layouts it wasn't written for.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MarkupVariant {
+  #[default]
+  Standard,
+  /// Thumbnails have no `data-md5` attribute.
+  NoMd5,
+  /// Posts have no file size or dimensions.
+  NoFileMetadata,
+  /// Hyperlinks are wrapped in another element rather than being direct children of the post text.
+  NestedLinks,
+  /// Thread pages have no posts at all.
+  NoPosts,
+}
+
+/// How many of each fault have been injected, served as JSON at `/_/mock/stats`.
+#[derive(Debug, Default)]
+pub struct FaultStats {
+  requests: AtomicU64,
+  rate_limited: AtomicU64,
+  slowed: AtomicU64,
+  truncated: AtomicU64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FaultStatsSnapshot {
+  pub requests: u64,
+  pub rate_limited: u64,
+  pub slowed: u64,
+  pub truncated: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Fault {
+  Request,
+  RateLimited,
+  Slowed,
+  Truncated,
+}
+
+impl MarkupVariant {
+  pub const NAMES: [&'static str; 5] = [
+    "standard",
+    "no-md5",
+    "no-file-metadata",
+    "nested-links",
+    "no-posts",
+  ];
+}
+
+impl FromStr for MarkupVariant {
+  type Err = anyhow::Error;
+
+  fn from_str(name: &str) -> Result<Self, Self::Err> {
+    match name {
+      "standard" => Ok(Self::Standard),
+      "no-md5" => Ok(Self::NoMd5),
+      "no-file-metadata" => Ok(Self::NoFileMetadata),
+      "nested-links" => Ok(Self::NestedLinks),
+      "no-posts" => Ok(Self::NoPosts),
+      _ => Err(anyhow::anyhow!("Unknown markup variant `{name}`.")),
+    }
+  }
+}
+
+impl FaultStats {
+  pub fn record(&self, fault: Fault) {
+    let counter = match fault {
+      Fault::Request => &self.requests,
+      Fault::RateLimited => &self.rate_limited,
+      Fault::Slowed => &self.slowed,
+      Fault::Truncated => &self.truncated,
+    };
+
+    counter.fetch_add(1, Ordering::Relaxed);
+  }
+
+  pub fn snapshot(&self) -> FaultStatsSnapshot {
+    FaultStatsSnapshot {
+      requests: self.requests.load(Ordering::Relaxed),
+      rate_limited: self.rate_limited.load(Ordering::Relaxed),
+      slowed: self.slowed.load(Ordering::Relaxed),
+      truncated: self.truncated.load(Ordering::Relaxed),
+    }
+  }
+}
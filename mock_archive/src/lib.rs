@@ -0,0 +1,11 @@
+//! A synthetic FoolFuuka archive, for testing the scraper without touching a real one.
+
+pub use crate::archive::{Archive, ArchiveSettings};
+pub use crate::faults::{Faults, MarkupVariant};
+pub use crate::server::{serve, spawn, RunningArchive};
+
+pub mod api;
+pub mod archive;
+pub mod faults;
+pub mod markup;
+pub mod server;
@@ -0,0 +1,23 @@
+use crate::clap::Args;
+use mock_archive::Archive;
+use tokio::net::TcpListener;
+
+pub mod clap;
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+  let args = Args::new();
+
+  args.get_logging_config().init()?;
+
+  let archive = Archive::generate(args.get_archive_settings());
+  let listener = TcpListener::bind(("127.0.0.1", args.get_port())).await?;
+
+  tracing::info!(
+    "Generated {} threads over {} search pages.",
+    archive.threads.len(),
+    archive.page_count()
+  );
+
+  mock_archive::serve(listener, archive, args.get_faults()).await
+}
@@ -0,0 +1,213 @@
+use crate::archive::{Archive, Media, Post, Thread};
+use crate::faults::MarkupVariant;
+use base64::Engine;
+use std::fmt::Write;
+
+/// Renders a page of search results, listing the opening post of each thread.
+pub fn search_page(archive: &Archive, page_number: usize, base_url: &str) -> String {
+  let board = &archive.settings.board;
+  let subject = &archive.settings.subject;
+  let threads = archive.search_page(page_number);
+  let mut body = String::new();
+
+  if threads.is_empty() {
+    body.push_str(r#"<div class="alert">No results found.</div>"#);
+  } else {
+    body.push_str(r#"<aside class="posts">"#);
+
+    for thread in threads {
+      let op = &thread.posts[0];
+      let _ = write!(
+        body,
+        r#"
+    <article class="post doc_id_{id} post_is_op{has_image}" id="{id}" data-board="{board}" data-doc-id="{id}">
+      <div class="post_wrapper">
+        <header>
+          <div class="post_data">
+            <h2 class="post_title">{subject}</h2>
+            {time}
+          </div>
+        </header>
+        <div class="text">{text}</div>
+      </div>
+    </article>"#,
+        id = op.id,
+        has_image = has_image_class(op),
+        time = time_wrap(op),
+        text = op.text,
+      );
+    }
+
+    body.push_str("\n  </aside>");
+  }
+
+  page(
+    &format!("/{board}/ &raquo; Search: Subject: {subject}"),
+    &format!(
+      r#"<h3 class="section_title">Searching for posts with the subject {subject}.</h3>
+  {body}
+  <div class="paginate"><a href="{base_url}/{board}/search/subject/{encoded_subject}/page/{next}">Next</a></div>"#,
+      encoded_subject = subject.replace('/', "%2F"),
+      next = page_number + 1,
+    ),
+  )
+}
+
+/// Renders a thread page, with the opening post followed by every reply.
+pub fn thread_page(
+  archive: &Archive,
+  thread: &Thread,
+  base_url: &str,
+  markup: MarkupVariant,
+) -> String {
+  let board = &archive.settings.board;
+  let title = format!("/{board}/ &raquo; Thread #{}", thread.id);
+
+  if markup == MarkupVariant::NoPosts {
+    return page(&title, r#"<div class="thread_content"></div>"#);
+  }
+
+  let op = &thread.posts[0];
+  let mut replies = String::new();
+
+  for post in &thread.posts[1..] {
+    replies.push_str(&post_article("post", post, archive, base_url, markup));
+  }
+
+  page(
+    &title,
+    &format!(
+      r#"<article class="clearfix thread doc_id_{id}{has_image}" id="{id}" data-thread-num="{id}" data-doc-id="{id}">
+  {op}
+  <aside class="posts">
+    <article class="backlink_container" id="backlink" style="position:absolute; top:0; left:0; display:none;"></article>
+    {replies}
+  </aside>
+</article>"#,
+      id = thread.id,
+      has_image = has_image_class(op),
+      op = post_article("post_is_op", op, archive, base_url, markup),
+    ),
+  )
+}
+
+fn post_article(
+  class: &str,
+  post: &Post,
+  archive: &Archive,
+  base_url: &str,
+  markup: MarkupVariant,
+) -> String {
+  let media = post
+    .media
+    .as_ref()
+    .map(|media| media_markup(media, base_url, markup))
+    .unwrap_or_default();
+  let mut text = post.text.clone();
+
+  for hyperlink in &post.hyperlinks {
+    let link = format!(r#"<a href="{hyperlink}" target="_blank" rel="nofollow">{hyperlink}</a>"#);
+
+    text.push_str("<br />");
+
+    if markup == MarkupVariant::NestedLinks {
+      let _ = write!(text, r#"<span class="quote">{link}</span>"#);
+    } else {
+      text.push_str(&link);
+    }
+  }
+
+  format!(
+    r#"
+    <article class="{class} doc_id_{id}{has_image}" id="{id}" data-board="{board}" data-doc-id="{id}">
+      <div class="post_wrapper">
+        <header>
+          <div class="post_data">
+            <span class="post_poster_data"><span class="post_author">Anonymous</span></span>
+            {time}
+          </div>
+        </header>{media}
+        <div class="text">{text}</div>
+      </div>
+    </article>"#,
+    id = post.id,
+    has_image = has_image_class(post),
+    board = archive.settings.board,
+    time = time_wrap(post),
+  )
+}
+
+fn media_markup(media: &Media, base_url: &str, markup: MarkupVariant) -> String {
+  let media_url = format!("{base_url}/media/{}", media.file_name);
+  let thumbnail_url = format!(
+    "{base_url}/thumb/{}s.jpg",
+    media.file_name.split('.').next().unwrap_or_default()
+  );
+  let file_metadata = if markup == MarkupVariant::NoFileMetadata {
+    String::new()
+  } else {
+    format!(
+      r#"<span class="post_file_metadata">{:.2} KiB, {}x{}</span>"#,
+      media.size as f64 / 1024.0,
+      media.width,
+      media.height
+    )
+  };
+  let md5_attribute = if markup == MarkupVariant::NoMd5 {
+    String::new()
+  } else {
+    format!(
+      r#" data-md5="{}""#,
+      base64::engine::general_purpose::STANDARD.encode(media.md5)
+    )
+  };
+
+  format!(
+    r#"
+        <div class="post_file">
+          {file_metadata}
+          <a href="{media_url}" class="post_file_filename" rel="tooltip" title="{name}.{extension}">{name}.{extension}</a>
+        </div>
+        <div class="thread_image_box">
+          <a href="{media_url}" target="_blank" rel="noreferrer" class="thread_image_link">
+            <img src="{thumbnail_url}" width="125" height="125" class="post_image"{md5_attribute} />
+          </a>
+        </div>"#,
+    name = media.original_name,
+    extension = media.extension,
+  )
+}
+
+fn time_wrap(post: &Post) -> String {
+  format!(
+    r#"<span class="time_wrap"><time datetime="{}">{}</time></span>"#,
+    post.posted_at.to_rfc3339(),
+    post.posted_at.format("%a %d %b %Y %H:%M:%S"),
+  )
+}
+
+fn has_image_class(post: &Post) -> &'static str {
+  if post.media.is_some() {
+    " has_image"
+  } else {
+    ""
+  }
+}
+
+fn page(title: &str, content: &str) -> String {
+  format!(
+    r#"<!DOCTYPE html>
+<html>
+<head>
+  <meta charset="utf-8" />
+  <title>{title}</title>
+</head>
+<body class="theme_default">
+<div role="main" id="main">
+{content}
+</div>
+</body>
+</html>
+"#
+  )
+}
@@ -0,0 +1,307 @@
+use crate::api;
+use crate::archive::Archive;
+use crate::faults::{Fault, FaultStats, FaultStatsSnapshot, Faults};
+use crate::markup;
+use axum::body::Body;
+use axum::extract::{Path, Query, Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{Html, IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use serde::Deserialize;
+use std::io;
+use std::sync::{Arc, Mutex};
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+
+const STATS_PATH: &str = "/_/mock/stats";
+
+/// Everything the server's handlers share.
+pub struct MockArchive {
+  archive: Archive,
+  faults: Faults,
+  base_url: String,
+  rng: Mutex<StdRng>,
+  stats: FaultStats,
+}
+
+/// A mock archive serving in the background, stopped when dropped.
+pub struct RunningArchive {
+  state: Arc<MockArchive>,
+  server_task: JoinHandle<()>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ThreadQuery {
+  board: String,
+  num: String,
+}
+
+impl MockArchive {
+  pub fn new(archive: Archive, faults: Faults, base_url: String) -> Self {
+    let rng = StdRng::seed_from_u64(archive.settings.seed);
+
+    Self {
+      archive,
+      faults,
+      base_url,
+      rng: Mutex::new(rng),
+      stats: FaultStats::default(),
+    }
+  }
+
+  pub fn base_url(&self) -> &str {
+    &self.base_url
+  }
+
+  /// The URL the scraper requests search pages under.
+  pub fn search_url(&self) -> String {
+    format!(
+      "{}/{}/search/subject/{}",
+      self.base_url,
+      self.archive.settings.board,
+      self.archive.settings.subject.replace('/', "%2F")
+    )
+  }
+
+  /// The URL the scraper appends thread IDs to.
+  pub fn thread_url(&self) -> String {
+    format!("{}/{}/thread/", self.base_url, self.archive.settings.board)
+  }
+
+  pub fn archive(&self) -> &Archive {
+    &self.archive
+  }
+
+  pub fn stats(&self) -> FaultStatsSnapshot {
+    self.stats.snapshot()
+  }
+
+  /// Rolls for a fault with the given chance.
+  fn roll(&self, chance: f64) -> bool {
+    self.rng.lock().unwrap().gen_bool(chance.clamp(0.0, 1.0))
+  }
+}
+
+impl RunningArchive {
+  pub fn base_url(&self) -> &str {
+    self.state.base_url()
+  }
+
+  pub fn search_url(&self) -> String {
+    self.state.search_url()
+  }
+
+  pub fn thread_url(&self) -> String {
+    self.state.thread_url()
+  }
+
+  pub fn archive(&self) -> &Archive {
+    self.state.archive()
+  }
+
+  pub fn stats(&self) -> FaultStatsSnapshot {
+    self.state.stats()
+  }
+}
+
+impl Drop for RunningArchive {
+  fn drop(&mut self) {
+    self.server_task.abort();
+  }
+}
+
+pub fn router(state: Arc<MockArchive>) -> Router {
+  Router::new()
+    .route(
+      "/{board}/search/subject/{subject}/page/{page_number}",
+      get(search_page),
+    )
+    .route("/{board}/thread/{thread_id}", get(thread_page))
+    .route("/media/{file_name}", get(media_file))
+    .route("/_/api/chan/thread/", get(api_thread))
+    .route(STATS_PATH, get(stats))
+    .layer(middleware::from_fn_with_state(state.clone(), inject_faults))
+    .with_state(state)
+}
+
+/// Serves the archive on the listener until the process ends.
+///
+/// # Errors
+/// - The listener's address couldn't be read, or serving failed.
+pub async fn serve(listener: TcpListener, archive: Archive, faults: Faults) -> anyhow::Result<()> {
+  let base_url = format!("http://{}", listener.local_addr()?);
+  let state = Arc::new(MockArchive::new(archive, faults, base_url));
+
+  tracing::info!(
+    "Serving a mock archive of {} threads at {}",
+    state.archive.threads.len(),
+    state.search_url()
+  );
+
+  axum::serve(listener, router(state)).await?;
+
+  Ok(())
+}
+
+/// Serves the archive on a free local port in the background.
+///
+/// # Errors
+/// - No local port could be bound.
+pub async fn spawn(archive: Archive, faults: Faults) -> anyhow::Result<RunningArchive> {
+  let listener = TcpListener::bind("127.0.0.1:0").await?;
+  let base_url = format!("http://{}", listener.local_addr()?);
+  let state = Arc::new(MockArchive::new(archive, faults, base_url));
+  let app = router(state.clone());
+
+  let server_task = tokio::spawn(async move {
+    if let Err(error) = axum::serve(listener, app).await {
+      tracing::error!("The mock archive stopped serving. Reason: `{error:?}`");
+    }
+  });
+
+  Ok(RunningArchive { state, server_task })
+}
+
+async fn inject_faults(
+  State(state): State<Arc<MockArchive>>,
+  request: Request,
+  next: Next,
+) -> Response {
+  if request.uri().path() == STATS_PATH {
+    return next.run(request).await;
+  }
+
+  state.stats.record(Fault::Request);
+
+  if state.roll(state.faults.slow_chance) {
+    state.stats.record(Fault::Slowed);
+    tokio::time::sleep(state.faults.slow_delay).await;
+  }
+
+  if state.roll(state.faults.rate_limit_chance) {
+    state.stats.record(Fault::RateLimited);
+
+    return (
+      StatusCode::TOO_MANY_REQUESTS,
+      [(header::RETRY_AFTER, "1")],
+      "Too many requests.",
+    )
+      .into_response();
+  }
+
+  next.run(request).await
+}
+
+async fn search_page(
+  State(state): State<Arc<MockArchive>>,
+  Path((board, _subject, page_number)): Path<(String, String, usize)>,
+) -> Response {
+  if board != state.archive.settings.board {
+    return StatusCode::NOT_FOUND.into_response();
+  }
+
+  Html(markup::search_page(
+    &state.archive,
+    page_number,
+    &state.base_url,
+  ))
+  .into_response()
+}
+
+async fn thread_page(
+  State(state): State<Arc<MockArchive>>,
+  Path((board, thread_id)): Path<(String, String)>,
+) -> Response {
+  let thread = state
+    .archive
+    .thread(&thread_id)
+    .filter(|_| board == state.archive.settings.board);
+
+  let Some(thread) = thread else {
+    return StatusCode::NOT_FOUND.into_response();
+  };
+
+  Html(markup::thread_page(
+    &state.archive,
+    thread,
+    &state.base_url,
+    state.faults.markup,
+  ))
+  .into_response()
+}
+
+async fn media_file(
+  State(state): State<Arc<MockArchive>>,
+  Path(file_name): Path<String>,
+) -> Response {
+  let Some(media) = state.archive.media(&file_name) else {
+    return StatusCode::NOT_FOUND.into_response();
+  };
+  let bytes = media.bytes();
+  let content_length = bytes.len().to_string();
+
+  if !state.roll(state.faults.truncate_chance) {
+    return (
+      [
+        (header::CONTENT_TYPE, "application/octet-stream".to_string()),
+        (header::CONTENT_LENGTH, content_length),
+      ],
+      bytes,
+    )
+      .into_response();
+  }
+
+  state.stats.record(Fault::Truncated);
+
+  // Half the body is sent before the connection is cut, short of the advertised length.
+  let half = bytes[..bytes.len() / 2].to_vec();
+  let body = Body::from_stream(futures::stream::iter([
+    Ok(half),
+    Err(io::Error::new(
+      io::ErrorKind::ConnectionAborted,
+      "Truncated by the mock archive.",
+    )),
+  ]));
+
+  (
+    [
+      (header::CONTENT_TYPE, "application/octet-stream".to_string()),
+      (header::CONTENT_LENGTH, content_length),
+    ],
+    body,
+  )
+    .into_response()
+}
+
+async fn api_thread(
+  State(state): State<Arc<MockArchive>>,
+  Query(query): Query<ThreadQuery>,
+) -> Response {
+  let thread = state
+    .archive
+    .thread(&query.num)
+    .filter(|_| query.board == state.archive.settings.board);
+
+  let Some(thread) = thread else {
+    return (
+      StatusCode::NOT_FOUND,
+      Json(serde_json::json!({ "error": "Thread not found." })),
+    )
+      .into_response();
+  };
+
+  Json(api::thread_response(
+    &state.archive,
+    thread,
+    &state.base_url,
+  ))
+  .into_response()
+}
+
+async fn stats(State(state): State<Arc<MockArchive>>) -> Json<FaultStatsSnapshot> {
+  Json(state.stats())
+}
@@ -1,5 +1,6 @@
 use clap::Arg;
 use clap::Command;
+use logging_setup::{ConsoleStream, LoggingConfig};
 use std::path::PathBuf;
 use std::sync::OnceLock;
 
@@ -41,6 +42,11 @@ impl Args {
     })
   }
 
+  /// Logs always go to stdout.
+  pub fn get_logging_config(&self) -> LoggingConfig {
+    LoggingConfig::from_matches(&self.args).with_console(ConsoleStream::Stdout)
+  }
+
   fn setup_args() -> clap::ArgMatches {
     Command::new("Recursively flattens all files under a directory to be at a given destination.")
       .arg(
@@ -57,6 +63,7 @@ impl Args {
           .action(clap::ArgAction::Set)
           .help("Sets a custom destination to move the files to."),
       )
+      .args(logging_setup::console_args())
       .get_matches()
   }
 }
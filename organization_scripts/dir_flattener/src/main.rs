@@ -1,18 +1,15 @@
 use crate::clap::Args;
 use std::fs;
 use std::path::{Path, PathBuf};
-use tracing::level_filters::LevelFilter;
 use walkdir::WalkDir;
 
 pub mod clap;
 
 fn main() {
-  tracing_subscriber::fmt()
-    .with_max_level(LevelFilter::INFO)
-    .with_ansi(false)
-    .init();
-
   let args = Args::new();
+
+  args.get_logging_config().init().unwrap();
+
   let directory_path = args.get_directory_path().to_str().unwrap();
   let destination = args
     .get_custom_destination()
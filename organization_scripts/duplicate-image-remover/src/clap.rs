@@ -1,5 +1,6 @@
 use clap::Arg;
 use clap::Command;
+use logging_setup::{ConsoleStream, LoggingConfig};
 use std::path::PathBuf;
 use std::sync::OnceLock;
 
@@ -45,6 +46,11 @@ impl Args {
     })
   }
 
+  /// Logs always go to stdout.
+  pub fn get_logging_config(&self) -> LoggingConfig {
+    LoggingConfig::from_matches(&self.args).with_console(ConsoleStream::Stdout)
+  }
+
   fn setup_args() -> clap::ArgMatches {
     Command::new("Just the surface level directory given for duplicate images, and moves them to the given destination.")
       .arg(
@@ -61,6 +67,7 @@ impl Args {
           .action(clap::ArgAction::Set)
           .help("Sets a custom destination to move the duplicate files to."),
       )
+      .args(logging_setup::console_args())
       .get_matches()
   }
 }
@@ -5,19 +5,16 @@ use std::collections::HashMap;
 use std::fs;
 use std::io::Write;
 use std::path::{Path, PathBuf};
-use tracing::level_filters::LevelFilter;
 
 pub mod clap;
 
 const DUPLICATE_LOG_FILE_PATH: &str = "duplicates.txt";
 
 fn main() {
-  tracing_subscriber::fmt()
-    .with_max_level(LevelFilter::INFO)
-    .with_ansi(false)
-    .init();
-
   let args = Args::new();
+
+  args.get_logging_config().init().unwrap();
+
   let directory_path = args.get_directory_path().to_str().unwrap();
   let destination = args
     .get_destination_path()
@@ -1,5 +1,6 @@
 use clap::Arg;
 use clap::Command;
+use logging_setup::{ConsoleStream, LoggingConfig};
 use std::path::PathBuf;
 use std::sync::OnceLock;
 
@@ -45,6 +46,11 @@ impl Args {
     })
   }
 
+  /// Logs always go to stdout.
+  pub fn get_logging_config(&self) -> LoggingConfig {
+    LoggingConfig::from_matches(&self.args).with_console(ConsoleStream::Stdout)
+  }
+
   fn setup_args() -> clap::ArgMatches {
     Command::new("Takes a directory and batch size to split files into different directories.")
       .arg(
@@ -61,6 +67,7 @@ impl Args {
           .action(clap::ArgAction::Set)
           .help("Sets the batch size for how many files will be in each directory."),
       )
+      .args(logging_setup::console_args())
       .get_matches()
   }
 }
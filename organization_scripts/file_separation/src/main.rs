@@ -3,17 +3,14 @@ use itertools::Itertools;
 use rand::{distributions::Alphanumeric, Rng};
 use std::fs::{self};
 use std::path::{Path, PathBuf};
-use tracing::level_filters::LevelFilter;
 
 pub mod clap;
 
 fn main() {
-  tracing_subscriber::fmt()
-    .with_max_level(LevelFilter::INFO)
-    .with_ansi(false)
-    .init();
-
   let args = Args::new();
+
+  args.get_logging_config().init().unwrap();
+
   let directory_path = args.get_directory_path().to_str().unwrap();
 
   let chunks = fs::read_dir(directory_path)
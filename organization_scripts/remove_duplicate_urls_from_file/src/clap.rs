@@ -1,5 +1,6 @@
 use clap::Arg;
 use clap::Command;
+use logging_setup::{ConsoleStream, LoggingConfig};
 use std::path::PathBuf;
 use std::sync::OnceLock;
 
@@ -30,6 +31,11 @@ impl Args {
     })
   }
 
+  /// Logs always go to stdout.
+  pub fn get_logging_config(&self) -> LoggingConfig {
+    LoggingConfig::from_matches(&self.args).with_console(ConsoleStream::Stdout)
+  }
+
   fn setup_args() -> clap::ArgMatches {
     Command::new("Compares a list of `name: url` in a given file and removes duplicate urls.")
       .arg(
@@ -39,6 +45,7 @@ impl Args {
           .action(clap::ArgAction::Set)
           .help("Takes the file to resort as an argument."),
       )
+      .args(logging_setup::console_args())
       .get_matches()
   }
 }
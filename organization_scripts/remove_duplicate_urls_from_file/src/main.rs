@@ -2,17 +2,14 @@ use clap::*;
 use std::fs;
 use std::io::Read;
 use std::io::Write;
-use tracing::level_filters::LevelFilter;
 
 pub mod clap;
 
 fn main() {
-  tracing_subscriber::fmt()
-    .with_max_level(LevelFilter::INFO)
-    .with_ansi(false)
-    .init();
-
   let args = Args::new();
+
+  args.get_logging_config().init().unwrap();
+
   let file_path = args.get_file_path();
 
   let mut file = fs::File::open(file_path).unwrap();
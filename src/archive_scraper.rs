@@ -0,0 +1,325 @@
+use crate::backend::ArchiveBackend;
+use crate::bandwidth::BandwidthLimiter;
+use crate::deferred_media::DeferredQueue;
+use crate::disk_guard::{DiskGuard, DiskSettings};
+use crate::download_plan::DownloadPlan;
+use crate::error::{ScrapeError, ScrapeResult};
+use crate::events::{EventSender, ScrapeEvent};
+use crate::failed_items::{ErrorClass, FailedQueue};
+use crate::http_client::HttpClient;
+use crate::media_filter::MediaFilter;
+use crate::media_index::MediaIndex;
+use crate::page_cache::PageCache;
+use crate::post_filter::PostFilter;
+use crate::progress::RunStats;
+use crate::ratelimiter::DeviationRateLimiter;
+use crate::scrape;
+use crate::scrape_context::ScrapeContext;
+use crate::scrape_state::ScrapeState;
+use crate::shutdown::ShutdownSignal;
+use crate::snapshot;
+use crate::storage::Storage;
+use crate::thread_list::read_thread_list;
+use crate::DEFAULT_BANNED_URL_LIST;
+use std::io::BufRead;
+use std::ops::RangeInclusive;
+use std::path::Path;
+use tokio::sync::mpsc::UnboundedReceiver;
+
+/// Scrapes media and hyperlinks from threads on a FoolFuuka archive.
+///
+/// example:
+/// ```no_run
+/// # async fn run() -> thread_archive_scraper::ScrapeResult<()> {
+/// use thread_archive_scraper::Scraper;
+///
+/// let scraper = Scraper::builder().dry_run(true).build()?;
+/// let mut events = scraper.subscribe();
+///
+/// tokio::spawn(async move {
+///   while let Some(event) = events.recv().await {
+///     println!("{event:?}");
+///   }
+/// });
+///
+/// scraper.scrape_page_range(1..=2).await;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct Scraper {
+  context: ScrapeContext,
+}
+
+#[derive(Default)]
+pub struct ScraperBuilder {
+  client: HttpClient,
+  rate_limiter: Option<DeviationRateLimiter>,
+  backend: ArchiveBackend,
+  storage: Storage,
+  disk_settings: DiskSettings,
+  media_filter: MediaFilter,
+  post_filter: PostFilter,
+  banned_urls: Option<Vec<String>>,
+  shutdown: ShutdownSignal,
+  dry_run: bool,
+  replay: bool,
+  disable_page_cache: bool,
+}
+
+impl Scraper {
+  pub fn builder() -> ScraperBuilder {
+    ScraperBuilder::default()
+  }
+
+  /// Receives every [`ScrapeEvent`] from this point on.
+  pub fn subscribe(&self) -> UnboundedReceiver<ScrapeEvent> {
+    self.context.events.subscribe()
+  }
+
+  pub fn run_stats(&self) -> &RunStats {
+    &self.context.run_stats
+  }
+
+  /// What would've been downloaded, if this is a dry run.
+  pub fn download_plan(&self) -> Option<&DownloadPlan> {
+    self.context.download_plan.as_ref()
+  }
+
+  /// Changes to it apply to downloads already in flight.
+  pub fn bandwidth_limiter(&self) -> &BandwidthLimiter {
+    self.context.client.bandwidth_limiter()
+  }
+
+  pub fn context(&self) -> &ScrapeContext {
+    &self.context
+  }
+
+  /// Scrapes every thread found on the search pages in the range.
+  ///
+  /// Returns where the run got to if it was stopped early.
+  pub async fn scrape_page_range(&self, page_range: RangeInclusive<usize>) -> Option<ScrapeState> {
+    let scrape_state = scrape::download_images_from_page_range(&self.context, page_range).await;
+    self.finish_run().await;
+
+    scrape_state
+  }
+
+  /// # Errors
+  /// - The thread page couldn't be fetched.
+  /// - Media from the thread failed to download, or hyperlinks failed to be written.
+  pub async fn scrape_thread(&self, thread_id: &str) -> ScrapeResult<()> {
+    let result =
+      scrape::download_images_and_urls_of_interest_from_thread(&self.context, thread_id).await;
+    self.finish_run().await;
+
+    result
+  }
+
+  /// Scrapes each of the threads, without going through search.
+  ///
+  /// Returns the threads left unprocessed if the run was stopped early.
+  pub async fn scrape_thread_ids(&self, thread_ids: &[String]) -> Vec<String> {
+    let unprocessed_thread_ids =
+      scrape::download_images_from_thread_ids(&self.context, thread_ids).await;
+    self.finish_run().await;
+
+    unprocessed_thread_ids.to_vec()
+  }
+
+  /// Scrapes every thread listed one per line, see [`read_thread_list`] for what a line can be.
+  ///
+  /// Returns the threads left unprocessed if the run was stopped early.
+  ///
+  /// # Errors
+  /// - The list couldn't be read.
+  pub async fn scrape_thread_list(&self, thread_list: impl BufRead) -> ScrapeResult<Vec<String>> {
+    let thread_ids = read_thread_list(thread_list, &self.context.backend.board)?;
+    tracing::info!("Read {} threads from the thread list.", thread_ids.len());
+
+    Ok(self.scrape_thread_ids(&thread_ids).await)
+  }
+
+  /// Reprocesses only the pages, threads and media that failed in earlier runs, optionally just those failing with one of the classes.
+  pub async fn retry_failed(&self, error_classes: &[ErrorClass]) {
+    scrape::retry_failed_items(&self.context, error_classes).await;
+    self.finish_run().await;
+  }
+
+  /// Downloads media from a file of `thread_id-post_id: url` lines.
+  ///
+  /// # Errors
+  /// - The file couldn't be read.
+  pub async fn download_file_list<P: AsRef<Path>>(&self, file_path: P) -> ScrapeResult<()> {
+    let result = scrape::download_images_from_file_list(&self.context, file_path).await;
+    self.finish_run().await;
+
+    result
+  }
+
+  /// Runs every thread with a saved snapshot back through parsing and extraction.
+  ///
+  /// # Errors
+  /// - [`ScrapeError::Config`] The scraper wasn't built to [`replay`](ScraperBuilder::replay).
+  /// - The data directory couldn't be read.
+  pub async fn replay_snapshots(&self) -> ScrapeResult<()> {
+    if !self.context.replay {
+      return Err(ScrapeError::Config(
+        "Replaying snapshots needs a scraper built with `replay(true)`.".to_string(),
+      ));
+    }
+
+    let thread_ids =
+      snapshot::saved_thread_ids(&self.context.storage, &self.context.backend.board)?;
+    tracing::info!("Replaying {} thread snapshots.", thread_ids.len());
+    scrape::download_images_from_thread_ids(&self.context, &thread_ids).await;
+    self.finish_run().await;
+
+    Ok(())
+  }
+
+  /// Retries media deferred while its host was down, then saves what's left and what failed for the next run along with the cookies.
+  ///
+  /// Dry runs and replays don't write anything, so they skip all of it.
+  /// Failing to save any of them is only logged, as the scrape itself is already done.
+  async fn finish_run(&self) {
+    if self.context.download_plan.is_some() || self.context.replay {
+      return;
+    }
+
+    scrape::download_deferred_media(&self.context).await;
+
+    if let Err(error) = self.context.deferred_media.save() {
+      tracing::error!("Failed to save the deferred media. Reason: `{error}`");
+    }
+
+    if let Err(error) = self.context.failed_items.save() {
+      tracing::error!("Failed to save the failed items. Reason: `{error}`");
+    }
+
+    if let Err(error) = self.context.client.save_cookies() {
+      tracing::error!("Failed to save cookies. Reason: `{error}`");
+    }
+  }
+}
+
+impl ScraperBuilder {
+  /// See [`HttpSettings::build_client`](crate::http_client::HttpSettings::build_client).
+  pub fn client(mut self, client: HttpClient) -> Self {
+    self.client = client;
+    self
+  }
+
+  pub fn rate_limiter(mut self, rate_limiter: DeviationRateLimiter) -> Self {
+    self.rate_limiter = Some(rate_limiter);
+    self
+  }
+
+  pub fn backend(mut self, backend: ArchiveBackend) -> Self {
+    self.backend = backend;
+    self
+  }
+
+  pub fn storage(mut self, storage: Storage) -> Self {
+    self.storage = storage;
+    self
+  }
+
+  pub fn disk_settings(mut self, disk_settings: DiskSettings) -> Self {
+    self.disk_settings = disk_settings;
+    self
+  }
+
+  pub fn media_filter(mut self, media_filter: MediaFilter) -> Self {
+    self.media_filter = media_filter;
+    self
+  }
+
+  pub fn post_filter(mut self, post_filter: PostFilter) -> Self {
+    self.post_filter = post_filter;
+    self
+  }
+
+  /// Replaces [`DEFAULT_BANNED_URL_LIST`].
+  pub fn banned_urls(mut self, banned_urls: Vec<String>) -> Self {
+    self.banned_urls = Some(banned_urls);
+    self
+  }
+
+  /// Defaults to a signal that never fires, use [`ShutdownSignal::listen`] to stop on Ctrl-C.
+  pub fn shutdown_signal(mut self, shutdown: ShutdownSignal) -> Self {
+    self.shutdown = shutdown;
+    self
+  }
+
+  /// Whether search and thread pages are cached and revalidated on later runs, which they are by default.
+  pub fn page_cache(mut self, enabled: bool) -> Self {
+    self.disable_page_cache = !enabled;
+    self
+  }
+
+  /// Collects what would be downloaded into a [`DownloadPlan`] instead of downloading or writing anything.
+  pub fn dry_run(mut self, dry_run: bool) -> Self {
+    self.dry_run = dry_run;
+    self
+  }
+
+  /// Reads search and thread pages from the snapshots saved by earlier runs, without sending anything to the archive.
+  ///
+  /// Links are extracted and written as usual, but no media is downloaded.
+  pub fn replay(mut self, replay: bool) -> Self {
+    self.replay = replay;
+    self
+  }
+
+  /// # Errors
+  /// - The rate limiter couldn't be built.
+  /// - The media index, deferred media or failed items couldn't be loaded from storage.
+  /// - The data directory couldn't be measured for the output limit.
+  pub fn build(self) -> ScrapeResult<Scraper> {
+    let rate_limiter = match self.rate_limiter {
+      Some(rate_limiter) => rate_limiter,
+      None => DeviationRateLimiter::new()?,
+    };
+    let media_index = MediaIndex::load(self.storage.media_index_path())?;
+    let mut deferred_media = DeferredQueue::load(self.storage.deferred_media_path())?;
+    let mut failed_items = FailedQueue::load(self.storage.failed_items_path())?;
+
+    // Dry runs and replays leave the queues for real runs.
+    if self.dry_run || self.replay {
+      deferred_media = deferred_media.without_appending();
+      failed_items = failed_items.without_appending();
+    }
+    let disk_guard = DiskGuard::new(self.disk_settings, &self.storage.data_dir)?;
+    let page_cache =
+      (!self.disable_page_cache).then(|| PageCache::new(self.storage.page_cache_dir()));
+    let banned_urls = self.banned_urls.unwrap_or_else(|| {
+      DEFAULT_BANNED_URL_LIST
+        .iter()
+        .map(|banned_url| banned_url.to_string())
+        .collect()
+    });
+
+    Ok(Scraper {
+      context: ScrapeContext {
+        client: self.client,
+        rate_limiter,
+        backend: self.backend,
+        storage: self.storage,
+        disk_guard,
+        media_index,
+        page_cache,
+        deferred_media,
+        failed_items,
+        media_filter: self.media_filter,
+        post_filter: self.post_filter,
+        banned_urls,
+        run_stats: RunStats::new(),
+        shutdown: self.shutdown,
+        events: EventSender::default(),
+        replay: self.replay,
+        download_plan: self.dry_run.then(DownloadPlan::default),
+      },
+    })
+  }
+}
@@ -0,0 +1,29 @@
+/// The FoolFuuka archive, board and search that threads are scraped from.
+#[derive(Debug, Clone)]
+pub struct ArchiveBackend {
+  /// The search results to find threads from, pages are requested at `{search_url}/page/{number}`.
+  pub search_url: String,
+  /// The URL thread IDs are appended to.
+  pub thread_url: String,
+  pub board: String,
+}
+
+impl ArchiveBackend {
+  pub fn search_page_url(&self, page_number: usize) -> String {
+    format!("{}/page/{}", self.search_url, page_number)
+  }
+
+  pub fn thread_page_url(&self, thread_id: &str) -> String {
+    format!("{}{}", self.thread_url, thread_id)
+  }
+}
+
+impl Default for ArchiveBackend {
+  fn default() -> Self {
+    Self {
+      search_url: "https://archive.palanq.win/vt/search/subject/%2Fshon%2F".to_string(),
+      thread_url: "https://archive.palanq.win/vt/thread/".to_string(),
+      board: "vt".to_string(),
+    }
+  }
+}
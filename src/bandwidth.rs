@@ -0,0 +1,136 @@
+use crate::circuit_breaker::host_of;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Caps on how fast media is downloaded, set from the `[bandwidth]` section of a profile.
+///
+/// example:
+/// ```toml
+/// [bandwidth]
+/// max_bytes_per_sec = 2097152
+///
+/// [bandwidth.hosts]
+/// "files.catbox.moe" = 524288
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BandwidthSettings {
+  /// Shared between every download. Unlimited if not set.
+  pub max_bytes_per_sec: Option<u64>,
+  /// Caps for single hosts, on top of the shared one.
+  pub hosts: BTreeMap<String, u64>,
+}
+
+/// Paces media bodies as they're read so they stay under the global and per-host caps.
+///
+/// Clones share their caps, so they can be changed from anywhere while a scrape is running.
+#[derive(Debug, Clone, Default)]
+pub struct BandwidthLimiter {
+  state: Arc<Mutex<BandwidthState>>,
+}
+
+#[derive(Debug, Default)]
+struct BandwidthState {
+  global: Option<Pace>,
+  hosts: HashMap<String, Pace>,
+}
+
+/// When the bytes read so far are paid off at the capped rate.
+#[derive(Debug)]
+struct Pace {
+  bytes_per_sec: u64,
+  paid_off_at: Instant,
+}
+
+impl BandwidthLimiter {
+  pub fn new(settings: &BandwidthSettings) -> Self {
+    let bandwidth_limiter = Self::default();
+    bandwidth_limiter.apply(settings);
+
+    bandwidth_limiter
+  }
+
+  /// Replaces every cap with the ones in the settings, taking effect from the next chunk read.
+  pub fn apply(&self, settings: &BandwidthSettings) {
+    let mut state = self.state.lock().unwrap();
+
+    state.global = match (state.global.take(), settings.max_bytes_per_sec) {
+      (_, None | Some(0)) => None,
+      (Some(pace), Some(bytes_per_sec)) => Some(Pace {
+        bytes_per_sec,
+        ..pace
+      }),
+      (None, Some(bytes_per_sec)) => Some(Pace::new(bytes_per_sec)),
+    };
+
+    state
+      .hosts
+      .retain(|host, _| settings.hosts.get(host).is_some_and(|limit| *limit > 0));
+
+    for (host, bytes_per_sec) in &settings.hosts {
+      if *bytes_per_sec == 0 {
+        continue;
+      }
+
+      state
+        .hosts
+        .entry(host.clone())
+        .and_modify(|pace| pace.bytes_per_sec = *bytes_per_sec)
+        .or_insert_with(|| Pace::new(*bytes_per_sec));
+    }
+
+    tracing::info!(
+      "Bandwidth capped at {} globally, with {} per-host caps.",
+      settings
+        .max_bytes_per_sec
+        .filter(|limit| *limit > 0)
+        .map_or("nothing".to_string(), |limit| format!("{limit} bytes/s")),
+      state.hosts.len()
+    );
+  }
+
+  /// Waits until the bytes just read from the URL fit under the caps.
+  ///
+  /// Returns how long was waited.
+  pub async fn throttle(&self, url: &str, bytes: usize) -> Duration {
+    let paid_off_at = {
+      let mut state = self.state.lock().unwrap();
+      let host = host_of(url);
+      let BandwidthState { global, hosts } = &mut *state;
+      let host_pace = host.and_then(|host| hosts.get_mut(&host));
+
+      [global.as_mut(), host_pace]
+        .into_iter()
+        .flatten()
+        .map(|pace| pace.pay(bytes as u64))
+        .max()
+    };
+
+    let Some(paid_off_at) = paid_off_at else {
+      return Duration::ZERO;
+    };
+    let wait_time = paid_off_at.saturating_duration_since(Instant::now());
+
+    tokio::time::sleep(wait_time).await;
+
+    wait_time
+  }
+}
+
+impl Pace {
+  fn new(bytes_per_sec: u64) -> Self {
+    Self {
+      bytes_per_sec,
+      paid_off_at: Instant::now(),
+    }
+  }
+
+  fn pay(&mut self, bytes: u64) -> Instant {
+    let cost = Duration::from_secs_f64(bytes as f64 / self.bytes_per_sec as f64);
+    self.paid_off_at = self.paid_off_at.max(Instant::now()) + cost;
+
+    self.paid_off_at
+  }
+}
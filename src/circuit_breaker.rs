@@ -0,0 +1,143 @@
+use crate::error::{ScrapeError, ScrapeResult};
+use reqwest::Url;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Stops sending requests to a host once it keeps failing, so work queued for it doesn't burn through every retry.
+///
+/// A host's circuit opens after `failure_threshold` consecutive failures.
+/// Once the probe interval passes a single request is let through to probe the host,
+/// closing the circuit if it succeeds and opening it again if it doesn't.
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+  failure_threshold: u32,
+  probe_interval: Duration,
+  hosts: Arc<Mutex<HashMap<String, HostHealth>>>,
+}
+
+#[derive(Debug, Default)]
+struct HostHealth {
+  consecutive_failures: u32,
+  circuit: Circuit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum Circuit {
+  #[default]
+  Closed,
+  /// No requests are sent until the probe is due.
+  Open { probe_at: Instant },
+  /// A probe request is in flight.
+  HalfOpen,
+}
+
+impl CircuitBreaker {
+  pub fn new(failure_threshold: u32, probe_interval: Duration) -> Self {
+    Self {
+      failure_threshold: failure_threshold.max(1),
+      probe_interval,
+      hosts: Arc::default(),
+    }
+  }
+
+  /// # Errors
+  /// - [`ScrapeError::HostUnavailable`] The URL's host is down, and isn't due to be probed.
+  pub fn check(&self, url: &str) -> ScrapeResult<()> {
+    let Some(host) = host_of(url) else {
+      return Ok(());
+    };
+    let mut hosts = self.hosts.lock().unwrap();
+    let Some(host_health) = hosts.get_mut(&host) else {
+      return Ok(());
+    };
+
+    match host_health.circuit {
+      Circuit::Closed => Ok(()),
+      Circuit::Open { probe_at } if Instant::now() >= probe_at => {
+        tracing::info!("Probing whether {host:?} is back up.");
+        host_health.circuit = Circuit::HalfOpen;
+
+        Ok(())
+      }
+      Circuit::Open { .. } | Circuit::HalfOpen => Err(ScrapeError::HostUnavailable { host }),
+    }
+  }
+
+  pub fn record_success(&self, url: &str) {
+    let Some(host) = host_of(url) else {
+      return;
+    };
+    let mut hosts = self.hosts.lock().unwrap();
+
+    if let Some(host_health) = hosts.remove(&host) {
+      if host_health.circuit != Circuit::Closed {
+        tracing::info!("{host:?} is back up.");
+      }
+    }
+  }
+
+  pub fn record_failure(&self, url: &str) {
+    let Some(host) = host_of(url) else {
+      return;
+    };
+    let mut hosts = self.hosts.lock().unwrap();
+    let host_health = hosts.entry(host.clone()).or_default();
+    host_health.consecutive_failures += 1;
+
+    let should_open = match host_health.circuit {
+      Circuit::HalfOpen => {
+        tracing::warn!(
+          "{host:?} is still down, probing again in {:?}.",
+          self.probe_interval
+        );
+
+        true
+      }
+      Circuit::Closed if host_health.consecutive_failures >= self.failure_threshold => {
+        tracing::warn!(
+          "{host:?} is down after {} consecutive failures, pausing requests to it for {:?}.",
+          host_health.consecutive_failures,
+          self.probe_interval
+        );
+
+        true
+      }
+      _ => false,
+    };
+
+    if should_open {
+      host_health.circuit = Circuit::Open {
+        probe_at: Instant::now() + self.probe_interval,
+      };
+    }
+  }
+
+  /// Waits until the URL's host is due to be probed, if its circuit is open.
+  pub async fn wait_for_probe(&self, url: &str) {
+    let Some(host) = host_of(url) else {
+      return;
+    };
+    let circuit = self
+      .hosts
+      .lock()
+      .unwrap()
+      .get(&host)
+      .map(|host_health| host_health.circuit);
+
+    if let Some(Circuit::Open { probe_at }) = circuit {
+      tokio::time::sleep_until(probe_at.into()).await;
+    }
+  }
+}
+
+/// The host a URL is sent to along with its port if it has one, if it's a valid URL.
+pub fn host_of(url: &str) -> Option<String> {
+  let url = Url::parse(url).ok()?;
+  let host = url.host_str()?;
+
+  match url.port() {
+    Some(port) => Some(format!("{host}:{port}")),
+    None => Some(host.to_string()),
+  }
+}
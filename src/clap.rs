@@ -0,0 +1,161 @@
+use crate::{LOG_DIR, LOG_FILE_NAME};
+use clap::builder::PossibleValuesParser;
+use clap::error::ErrorKind;
+use clap::parser::ValueSource;
+use clap::Arg;
+use clap::Command;
+use logging_setup::LoggingConfig;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use thread_archive_scraper::failed_items::ErrorClass;
+
+pub struct Args {
+  args: clap::ArgMatches,
+}
+
+impl Args {
+  const PROFILE: &'static str = "profile";
+  const DRY_RUN: &'static str = "dry_run";
+  const NO_PAGE_CACHE: &'static str = "no_page_cache";
+  const REPLAY: &'static str = "replay";
+  const THREAD_LIST: &'static str = "thread_list";
+  const RETRY_FAILED: &'static str = "retry-failed";
+  const ERROR_CLASS: &'static str = "error_class";
+
+  pub fn new() -> Self {
+    let args = Self::setup_args();
+
+    Self { args }
+  }
+
+  pub fn get_profile_path(&self) -> &'static Option<PathBuf> {
+    static LOCK: OnceLock<Option<PathBuf>> = OnceLock::new();
+
+    LOCK.get_or_init(|| {
+      self
+        .args
+        .get_one::<String>(Self::PROFILE)
+        .map(PathBuf::from)
+    })
+  }
+
+  pub fn get_dry_run(&self) -> bool {
+    self.args.get_flag(Self::DRY_RUN)
+  }
+
+  pub fn get_no_page_cache(&self) -> bool {
+    self.args.get_flag(Self::NO_PAGE_CACHE)
+  }
+
+  pub fn get_replay(&self) -> bool {
+    self.args.get_flag(Self::REPLAY)
+  }
+
+  /// `-` means the list is read from stdin.
+  pub fn get_thread_list_path(&self) -> Option<&String> {
+    self.args.get_one::<String>(Self::THREAD_LIST)
+  }
+
+  /// The error classes to retry if the `retry-failed` command was given, where none means every class.
+  pub fn get_retry_failed_classes(&self) -> Option<Vec<ErrorClass>> {
+    let retry_failed_args = self.args.subcommand_matches(Self::RETRY_FAILED)?;
+
+    Some(
+      retry_failed_args
+        .get_many::<String>(Self::ERROR_CLASS)
+        .into_iter()
+        .flatten()
+        .filter_map(|error_class| error_class.parse().ok())
+        .collect(),
+    )
+  }
+
+  /// Logs always go to the daily log file, and additionally to the console when asked for.
+  pub fn get_logging_config(&self) -> LoggingConfig {
+    LoggingConfig::from_matches(&self.args).with_log_file(LOG_DIR, LOG_FILE_NAME)
+  }
+
+  fn setup_args() -> clap::ArgMatches {
+    let mut command = Self::command();
+    let args = command.get_matches_mut();
+
+    // Clap can't have a subcommand conflict with its parent's args, so it's checked here.
+    if args.subcommand_matches(Self::RETRY_FAILED).is_some() {
+      for (arg_id, flag) in [
+        (Self::REPLAY, "--replay"),
+        (Self::THREAD_LIST, "--thread-list"),
+      ] {
+        if args.value_source(arg_id) == Some(ValueSource::CommandLine) {
+          command
+            .error(
+              ErrorKind::ArgumentConflict,
+              format!(
+                "the argument '{flag}' cannot be used with the '{}' command",
+                Self::RETRY_FAILED
+              ),
+            )
+            .exit();
+        }
+      }
+    }
+
+    args
+  }
+
+  fn command() -> Command {
+    Command::new("Downloads media and hyperlinks of interest from threads on a FoolFuuka archive.")
+      .arg(
+        Arg::new(Self::PROFILE)
+          .short('p')
+          .long("profile")
+          .action(clap::ArgAction::Set)
+          .help("Sets the TOML profile to configure the scrape with."),
+      )
+      .arg(
+        Arg::new(Self::DRY_RUN)
+          .long("dry-run")
+          .action(clap::ArgAction::SetTrue)
+          .help("Fetches pages and reports what would be downloaded, without downloading or writing anything."),
+      )
+      .arg(
+        Arg::new(Self::NO_PAGE_CACHE)
+          .long("no-page-cache")
+          .action(clap::ArgAction::SetTrue)
+          .help("Downloads every search and thread page in full, rather than revalidating cached copies."),
+      )
+      .arg(
+        Arg::new(Self::REPLAY)
+          .long("replay")
+          .action(clap::ArgAction::SetTrue)
+          .help("Extracts links again from every saved thread snapshot, without contacting the archive or downloading media."),
+      )
+      .arg(
+        Arg::new(Self::THREAD_LIST)
+          .short('t')
+          .long("thread-list")
+          .action(clap::ArgAction::Set)
+          .conflicts_with(Self::REPLAY)
+          .help("Scrapes the thread IDs or thread URLs listed one per line in the file, or stdin if `-`, instead of searching."),
+      )
+      .args(logging_setup::args())
+      .subcommand(
+        Command::new(Self::RETRY_FAILED)
+          .about("Reprocesses only the pages, threads and media that failed in earlier runs.")
+          .arg(
+            Arg::new(Self::ERROR_CLASS)
+              .long("class")
+              .action(clap::ArgAction::Append)
+              .value_parser(PossibleValuesParser::new(
+                ErrorClass::ALL.map(|error_class| error_class.name()),
+              ))
+              .help("Only retries items that failed with this class of error. Can be given more than once."),
+          ),
+      )
+  }
+}
+
+impl Default for Args {
+  fn default() -> Self {
+    Self::new()
+  }
+}
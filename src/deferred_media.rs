@@ -0,0 +1,48 @@
+use crate::html_parsing::{MediaData, PostContext};
+use crate::json_lines_queue::{JsonLinesQueue, QueueItem};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Media put off because its host was down.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeferredMedia {
+  pub board: String,
+  pub thread_id: String,
+  pub post_id: String,
+  pub timestamp: Option<DateTime<Utc>>,
+  pub file_appender: String,
+  pub media: MediaData,
+}
+
+/// Media waiting on a host to come back up, retried at the end of a run.
+///
+/// Whatever is still waiting when the run ends is kept as a JSON line file, and picked up again by the next run.
+pub type DeferredQueue = JsonLinesQueue<DeferredMedia>;
+
+impl DeferredMedia {
+  pub fn new(post: &PostContext<'_>, media: MediaData, file_appender: &str) -> Self {
+    Self {
+      board: post.board.to_string(),
+      thread_id: post.thread_id.to_string(),
+      post_id: post.post_id.to_string(),
+      timestamp: post.timestamp,
+      file_appender: file_appender.to_string(),
+      media,
+    }
+  }
+
+  pub fn post_context(&self) -> PostContext<'_> {
+    PostContext {
+      board: &self.board,
+      thread_id: &self.thread_id,
+      post_id: &self.post_id,
+      timestamp: self.timestamp,
+    }
+  }
+}
+
+impl QueueItem for DeferredMedia {
+  fn is_same_item(&self, other: &Self) -> bool {
+    self.post_id == other.post_id && self.media.url == other.media.url
+  }
+}
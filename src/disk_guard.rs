@@ -0,0 +1,128 @@
+use crate::error::{ScrapeError, ScrapeResult};
+use indicatif::HumanBytes;
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Limits on how much a scrape writes to disk, set from the `[disk]` section of a profile.
+///
+/// example:
+/// ```toml
+/// [disk]
+/// max_output_bytes = 500_000_000_000
+/// min_free_bytes = 10_000_000_000
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DiskSettings {
+  /// The most the data directory can hold, counting what was there before the run.
+  pub max_output_bytes: Option<u64>,
+  /// The least free space to leave on the disk the data directory is on.
+  pub min_free_bytes: Option<u64>,
+}
+
+/// Checks each download fits under the [`DiskSettings`] before it's written.
+#[derive(Debug, Clone, Default)]
+pub struct DiskGuard {
+  settings: DiskSettings,
+  data_dir: PathBuf,
+  /// Only tracked when there's a maximum output size.
+  output_bytes: Arc<AtomicU64>,
+}
+
+impl DiskGuard {
+  /// Measures what's already in the data directory if there's a maximum output size.
+  ///
+  /// # Errors
+  /// - The data directory exists but couldn't be read.
+  pub fn new(settings: DiskSettings, data_dir: impl Into<PathBuf>) -> ScrapeResult<Self> {
+    let data_dir = data_dir.into();
+    let output_bytes = match settings.max_output_bytes {
+      Some(_) if data_dir.exists() => directory_size(&data_dir)?,
+      _ => 0,
+    };
+
+    if let Some(max_output_bytes) = settings.max_output_bytes {
+      tracing::info!(
+        "{} of the {} output limit is already used.",
+        HumanBytes(output_bytes),
+        HumanBytes(max_output_bytes)
+      );
+    }
+
+    Ok(Self {
+      settings,
+      data_dir,
+      output_bytes: Arc::new(AtomicU64::new(output_bytes)),
+    })
+  }
+
+  /// Checks that `upcoming_bytes` more can be written.
+  ///
+  /// # Errors
+  /// - [`ScrapeError::DiskLimit`] Writing them would go over the output limit or under the free space threshold.
+  /// - The free space couldn't be read.
+  pub fn check(&self, upcoming_bytes: u64) -> ScrapeResult<()> {
+    if let Some(max_output_bytes) = self.settings.max_output_bytes {
+      let output_bytes = self.output_bytes.load(Ordering::Relaxed);
+
+      if output_bytes + upcoming_bytes > max_output_bytes {
+        return Err(ScrapeError::DiskLimit(format!(
+          "The data directory holds {} and {} more would go over the {} output limit.",
+          HumanBytes(output_bytes),
+          HumanBytes(upcoming_bytes),
+          HumanBytes(max_output_bytes)
+        )));
+      }
+    }
+
+    if let Some(min_free_bytes) = self.settings.min_free_bytes {
+      let free_bytes = fs2::available_space(existing_ancestor(&self.data_dir))?;
+
+      if free_bytes.saturating_sub(upcoming_bytes) < min_free_bytes {
+        return Err(ScrapeError::DiskLimit(format!(
+          "{} is free and {} more would go under the {} free space threshold.",
+          HumanBytes(free_bytes),
+          HumanBytes(upcoming_bytes),
+          HumanBytes(min_free_bytes)
+        )));
+      }
+    }
+
+    Ok(())
+  }
+
+  pub fn record_written(&self, bytes: u64) {
+    self.output_bytes.fetch_add(bytes, Ordering::Relaxed);
+  }
+}
+
+fn directory_size(directory: &Path) -> ScrapeResult<u64> {
+  let mut size = 0;
+  let mut directories = vec![directory.to_path_buf()];
+
+  while let Some(current_directory) = directories.pop() {
+    for entry in fs::read_dir(current_directory)? {
+      let entry = entry?;
+      let metadata = entry.metadata()?;
+
+      if metadata.is_dir() {
+        directories.push(entry.path());
+      } else {
+        size += metadata.len();
+      }
+    }
+  }
+
+  Ok(size)
+}
+
+/// The data directory might not have been created yet, so free space is read from the closest directory that has.
+fn existing_ancestor(path: &Path) -> &Path {
+  path
+    .ancestors()
+    .find(|ancestor| ancestor.exists())
+    .unwrap_or(Path::new("."))
+}
@@ -0,0 +1,112 @@
+use indicatif::HumanBytes;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// Everything a dry run would've downloaded or written, collected instead of touching the disk.
+#[derive(Debug, Clone, Default)]
+pub struct DownloadPlan {
+  items: Arc<Mutex<Vec<PlannedItem>>>,
+}
+
+#[derive(Debug, Clone)]
+pub enum PlannedItem {
+  Media {
+    thread_id: String,
+    post_id: String,
+    url: String,
+    target_path: PathBuf,
+    estimated_size: Option<u64>,
+    /// Where the media already is, if it wouldn't need to be downloaded.
+    existing_path: Option<PathBuf>,
+  },
+  Hyperlinks {
+    thread_id: String,
+    post_id: String,
+    hyperlinks: Vec<String>,
+    target_path: PathBuf,
+  },
+}
+
+impl DownloadPlan {
+  pub fn add(&self, item: PlannedItem) {
+    self.items.lock().unwrap().push(item);
+  }
+
+  /// Renders every planned item followed by totals.
+  pub fn report(&self) -> String {
+    let items = self.items.lock().unwrap();
+    let mut lines = vec![];
+    let mut media_count = 0;
+    let mut already_downloaded_count = 0;
+    let mut unknown_size_count = 0;
+    let mut estimated_total_size = 0;
+    let mut hyperlink_count = 0;
+
+    for item in items.iter() {
+      match item {
+        PlannedItem::Media {
+          thread_id,
+          post_id,
+          url,
+          target_path,
+          estimated_size,
+          existing_path,
+        } => {
+          if let Some(existing_path) = existing_path {
+            already_downloaded_count += 1;
+            lines.push(format!(
+              "{thread_id}-{post_id}: {url} -> {} (already at {})",
+              target_path.display(),
+              existing_path.display()
+            ));
+
+            continue;
+          }
+
+          media_count += 1;
+          let estimated_size = match estimated_size {
+            Some(estimated_size) => {
+              estimated_total_size += estimated_size;
+              format!("~{}", HumanBytes(*estimated_size))
+            }
+            None => {
+              unknown_size_count += 1;
+              "unknown size".to_string()
+            }
+          };
+
+          lines.push(format!(
+            "{thread_id}-{post_id}: {url} -> {} ({estimated_size})",
+            target_path.display()
+          ));
+        }
+        PlannedItem::Hyperlinks {
+          thread_id,
+          post_id,
+          hyperlinks,
+          target_path,
+        } => {
+          hyperlink_count += hyperlinks.len();
+          lines.extend(hyperlinks.iter().map(|hyperlink| {
+            format!(
+              "{thread_id}-{post_id}: {hyperlink} >> {}",
+              target_path.display()
+            )
+          }));
+        }
+      }
+    }
+
+    lines.push(String::new());
+    lines.push(format!(
+      "Would download {media_count} media files, ~{} ({unknown_size_count} of unknown size).",
+      HumanBytes(estimated_total_size)
+    ));
+    lines.push(format!(
+      "{already_downloaded_count} media files are already downloaded."
+    ));
+    lines.push(format!("Would write {hyperlink_count} hyperlinks."));
+
+    lines.join("\n")
+  }
+}
@@ -0,0 +1,107 @@
+use crate::media_filter::FilterRejection;
+use crate::verification::Verification;
+use reqwest::StatusCode;
+use std::path::PathBuf;
+
+pub type ScrapeResult<T> = Result<T, ScrapeError>;
+
+/// Everything that can go wrong while scraping.
+#[derive(Debug, thiserror::Error)]
+pub enum ScrapeError {
+  /// No response could be gotten, or the body couldn't be read.
+  #[error("Request to `{url}` failed. Reason: `{source}`")]
+  Network {
+    url: String,
+    #[source]
+    source: reqwest::Error,
+  },
+  /// The archive responded with something other than a success.
+  #[error("`{url}` responded with status {status}.")]
+  HttpStatus { url: String, status: StatusCode },
+  /// A page didn't look the way it was expected to, most likely because the archive's markup changed.
+  #[error("Unexpected markup from `{url}`. {reason}")]
+  Markup { url: String, reason: String },
+  #[error(transparent)]
+  Io(#[from] std::io::Error),
+  #[error(transparent)]
+  Json(#[from] serde_json::Error),
+  /// A path template, profile or other setting is invalid.
+  #[error("Invalid configuration. {0}")]
+  Config(String),
+  #[error("Media was filtered out, {0}.")]
+  FilterRejected(FilterRejection),
+  /// Media kept failing verification, the last failed download is described by `verification`.
+  #[error("Media from `{url}` failed verification after {attempts} attempts.")]
+  VerificationFailed {
+    url: String,
+    attempts: usize,
+    verification: Verification,
+  },
+  /// A transfer slowed below the minimum throughput, see [`StallWatchdog`](crate::http_client::StallWatchdog).
+  #[error("Transfer of `{url}` stalled at {bytes_per_sec} bytes/s after {received} bytes.")]
+  Stalled {
+    url: String,
+    bytes_per_sec: u64,
+    received: u64,
+  },
+  /// Writing more would go over a [`DiskSettings`](crate::disk_guard::DiskSettings) limit.
+  #[error("Disk limit reached. {0}")]
+  DiskLimit(String),
+  /// The host's robots.txt asks for the URL not to be crawled.
+  #[error("`{url}` is disallowed by robots.txt.")]
+  Disallowed { url: String },
+  /// Requests to the host are paused after it kept failing, see [`CircuitBreaker`](crate::circuit_breaker::CircuitBreaker).
+  #[error("`{host}` is down, requests to it are paused.")]
+  HostUnavailable { host: String },
+  /// Replaying needs a snapshot of the page, and none was saved when it was last fetched.
+  #[error("No snapshot of `{url}` was saved at {path:?}.")]
+  MissingSnapshot { url: String, path: PathBuf },
+  #[error("Download of `{url}` was aborted by shutdown.")]
+  Aborted { url: String },
+}
+
+impl ScrapeError {
+  pub fn network(url: impl Into<String>, source: reqwest::Error) -> Self {
+    Self::Network {
+      url: url.into(),
+      source,
+    }
+  }
+
+  /// Whether the archive is refusing requests for being sent too many.
+  pub fn is_rate_limited(&self) -> bool {
+    matches!(
+      self,
+      Self::HttpStatus {
+        status: StatusCode::TOO_MANY_REQUESTS,
+        ..
+      }
+    )
+  }
+
+  /// Whether the failure counts against the health of the host the request was sent to.
+  pub fn is_host_failure(&self) -> bool {
+    match self {
+      Self::Network { .. } | Self::Stalled { .. } => true,
+      Self::HttpStatus { status, .. } => status.is_server_error(),
+      _ => false,
+    }
+  }
+
+  /// Whether trying the same request again could succeed.
+  pub fn is_retryable(&self) -> bool {
+    match self {
+      Self::Network { .. } | Self::Stalled { .. } => true,
+      Self::HttpStatus { status, .. } => {
+        *status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+      }
+      _ => false,
+    }
+  }
+}
+
+impl From<FilterRejection> for ScrapeError {
+  fn from(rejection: FilterRejection) -> Self {
+    Self::FilterRejected(rejection)
+  }
+}
@@ -0,0 +1,58 @@
+use crate::html_parsing::MediaData;
+use chrono::{DateTime, Utc};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+/// What a scrape discovers and does as it runs.
+#[derive(Debug, Clone)]
+pub enum ScrapeEvent {
+  ThreadsDiscovered {
+    page_number: usize,
+    thread_ids: Vec<String>,
+  },
+  PostDiscovered {
+    thread_id: String,
+    post_id: String,
+    timestamp: Option<DateTime<Utc>>,
+  },
+  /// Media that passed the filters and is about to be downloaded.
+  MediaDiscovered {
+    thread_id: String,
+    post_id: String,
+    media: MediaData,
+  },
+  HyperlinksDiscovered {
+    thread_id: String,
+    post_id: String,
+    hyperlinks: Vec<String>,
+  },
+  /// Media saved to disk, including duplicates linked to or referenced from an earlier copy.
+  MediaSaved {
+    thread_id: String,
+    post_id: String,
+    path: PathBuf,
+  },
+}
+
+/// Sends [`ScrapeEvent`]s to everything subscribed to a scrape.
+#[derive(Debug, Clone, Default)]
+pub struct EventSender {
+  subscribers: Arc<Mutex<Vec<UnboundedSender<ScrapeEvent>>>>,
+}
+
+impl EventSender {
+  pub fn subscribe(&self) -> UnboundedReceiver<ScrapeEvent> {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    self.subscribers.lock().unwrap().push(sender);
+
+    receiver
+  }
+
+  /// Sends the event to every subscriber, forgetting any that have stopped listening.
+  pub fn send(&self, event: ScrapeEvent) {
+    let mut subscribers = self.subscribers.lock().unwrap();
+
+    subscribers.retain(|subscriber| subscriber.send(event.clone()).is_ok());
+  }
+}
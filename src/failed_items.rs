@@ -0,0 +1,154 @@
+use crate::deferred_media::DeferredMedia;
+use crate::error::ScrapeError;
+use crate::json_lines_queue::{JsonLinesQueue, QueueItem};
+use chrono::{DateTime, Utc};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// A page, thread or media download that failed, kept so it can be retried later.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailedItem {
+  #[serde(flatten)]
+  pub target: FailedTarget,
+  pub error_class: ErrorClass,
+  pub reason: String,
+  pub failed_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FailedTarget {
+  /// A search page, none of whose threads were scraped.
+  Page {
+    page_number: usize,
+  },
+  Thread {
+    thread_id: String,
+  },
+  Media(Box<DeferredMedia>),
+}
+
+/// The broad kind of error an item failed with, for choosing which failures to retry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorClass {
+  /// No response, a stalled transfer, or a host that was down.
+  Network,
+  RateLimited,
+  ServerError,
+  /// Any other unsuccessful status, such as a 404.
+  ClientError,
+  Markup,
+  Verification,
+  Disallowed,
+  Io,
+  Other,
+}
+
+/// Every item that failed, across runs.
+///
+/// Items are kept as a JSON line file until a retry gets them through.
+pub type FailedQueue = JsonLinesQueue<FailedItem>;
+
+impl FailedTarget {
+  fn is_same_item(&self, other: &Self) -> bool {
+    match (self, other) {
+      (Self::Page { page_number }, Self::Page { page_number: other }) => page_number == other,
+      (Self::Thread { thread_id }, Self::Thread { thread_id: other }) => thread_id == other,
+      (Self::Media(media), Self::Media(other)) => media.is_same_item(other),
+      _ => false,
+    }
+  }
+}
+
+impl ErrorClass {
+  pub const ALL: [Self; 9] = [
+    Self::Network,
+    Self::RateLimited,
+    Self::ServerError,
+    Self::ClientError,
+    Self::Markup,
+    Self::Verification,
+    Self::Disallowed,
+    Self::Io,
+    Self::Other,
+  ];
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      Self::Network => "network",
+      Self::RateLimited => "rate_limited",
+      Self::ServerError => "server_error",
+      Self::ClientError => "client_error",
+      Self::Markup => "markup",
+      Self::Verification => "verification",
+      Self::Disallowed => "disallowed",
+      Self::Io => "io",
+      Self::Other => "other",
+    }
+  }
+}
+
+impl From<&ScrapeError> for ErrorClass {
+  fn from(error: &ScrapeError) -> Self {
+    match error {
+      ScrapeError::Network { .. }
+      | ScrapeError::Stalled { .. }
+      | ScrapeError::HostUnavailable { .. } => Self::Network,
+      ScrapeError::HttpStatus { status, .. } if *status == StatusCode::TOO_MANY_REQUESTS => {
+        Self::RateLimited
+      }
+      ScrapeError::HttpStatus { status, .. } if status.is_server_error() => Self::ServerError,
+      ScrapeError::HttpStatus { .. } => Self::ClientError,
+      ScrapeError::Markup { .. } => Self::Markup,
+      ScrapeError::VerificationFailed { .. } => Self::Verification,
+      ScrapeError::Disallowed { .. } => Self::Disallowed,
+      ScrapeError::Io(_) | ScrapeError::Json(_) => Self::Io,
+      _ => Self::Other,
+    }
+  }
+}
+
+impl fmt::Display for ErrorClass {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(self.name())
+  }
+}
+
+impl FromStr for ErrorClass {
+  type Err = ScrapeError;
+
+  fn from_str(name: &str) -> Result<Self, Self::Err> {
+    Self::ALL
+      .into_iter()
+      .find(|error_class| error_class.name() == name)
+      .ok_or_else(|| ScrapeError::Config(format!("{name:?} isn't an error class.")))
+  }
+}
+
+impl QueueItem for FailedItem {
+  fn is_same_item(&self, other: &Self) -> bool {
+    self.target.is_same_item(&other.target)
+  }
+}
+
+impl FailedQueue {
+  /// Adds the failure, replacing any earlier one for the same item.
+  pub fn record(&self, target: FailedTarget, error: &ScrapeError) {
+    self.replace(FailedItem {
+      target,
+      error_class: error.into(),
+      reason: error.to_string(),
+      failed_at: Utc::now(),
+    });
+  }
+
+  /// Removes and returns the items that failed with any of the classes, or every item if none are given.
+  pub fn take_matching(&self, error_classes: &[ErrorClass]) -> Vec<FailedItem> {
+    self.take_where(|failed_item| {
+      error_classes.is_empty() || error_classes.contains(&failed_item.error_class)
+    })
+  }
+}
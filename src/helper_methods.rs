@@ -1,42 +1,195 @@
+use crate::error::{ScrapeError, ScrapeResult};
+use crate::http_client::HttpClient;
 use crate::ratelimiter::DeviationRateLimiter;
-use anyhow::anyhow;
-use reqwest::{Client, Response};
+use bytes::Bytes;
+use reqwest::header::HeaderMap;
+use reqwest::{Response, StatusCode};
 use std::time::Duration;
 
 /// Sends a GET request to the desired URL, retrying with the desired amount of times if it fails.
 ///
 /// Every failed attempt will wait for the passed in time.
+/// Only failures that could succeed on another attempt are retried, see [`ScrapeError::is_retryable`].
+/// Every attempt is reported to the client's [`CircuitBreaker`](crate::circuit_breaker::CircuitBreaker).
+/// The URL is checked against the host's robots.txt first, unless the client ignores it,
+/// and a robots.txt that couldn't be gotten fails the attempt.
 ///
 /// # Errors
-/// - Failed to get a response after the desired amount of attempts.
+/// - [`ScrapeError::Disallowed`] The host's robots.txt disallows the URL.
+/// - [`ScrapeError::HostUnavailable`] The host is down, so no more requests are sent to it.
+/// - [`ScrapeError::Network`] No response was gotten after the desired amount of attempts.
+/// - [`ScrapeError::HttpStatus`] The response wasn't a success.
 pub async fn get_with_retry(
-  client: &Client,
+  client: &HttpClient,
   request_url: String,
   retry_count: usize,
   rate_limiter: &DeviationRateLimiter,
   wait_time: Duration,
-) -> anyhow::Result<Response> {
+) -> ScrapeResult<Response> {
+  get_with_retry_and_headers(
+    client,
+    request_url,
+    &HeaderMap::new(),
+    retry_count,
+    rate_limiter,
+    wait_time,
+  )
+  .await
+}
+
+/// [`get_with_retry`], with extra headers sent on every attempt.
+///
+/// A `304 Not Modified` counts as a success, for conditional requests.
+///
+/// # Errors
+/// - Everything [`get_with_retry`] returns.
+pub async fn get_with_retry_and_headers(
+  client: &HttpClient,
+  request_url: String,
+  headers: &HeaderMap,
+  retry_count: usize,
+  rate_limiter: &DeviationRateLimiter,
+  wait_time: Duration,
+) -> ScrapeResult<Response> {
+  let mut last_error = None;
+
   for iteration in 1..=retry_count {
-    rate_limiter.wait().await;
-    let result = client.get(&request_url).send().await;
+    let error = match attempt_request(client, &request_url, headers, rate_limiter).await {
+      Ok(response) => return Ok(response),
+      Err(error) => error,
+    };
+
+    if !error.is_retryable() {
+      return Err(error);
+    }
+
+    tracing::warn!(
+      "Failed to get a response from {:?}. {} more attempts left. Reason: `{error}`",
+      request_url,
+      retry_count - iteration
+    );
+    last_error = Some(error);
+    tokio::time::sleep(wait_time).await;
+  }
+
+  Err(last_error.unwrap_or_else(|| ScrapeError::Config("The retry count can't be 0.".to_string())))
+}
+
+/// Sends a single attempt at the URL, reporting how it went to the client's circuit breaker.
+async fn attempt_request(
+  client: &HttpClient,
+  request_url: &str,
+  headers: &HeaderMap,
+  rate_limiter: &DeviationRateLimiter,
+) -> ScrapeResult<Response> {
+  let circuit_breaker = client.circuit_breaker();
+  circuit_breaker.check(request_url)?;
+
+  let error = match send_request(client, request_url, headers, rate_limiter).await {
+    Ok(response) => {
+      circuit_breaker.record_success(request_url);
 
-    if let Ok(response) = result {
       return Ok(response);
-    } else {
-      tracing::warn!(
-        "Failed to get a response from {:?}. {} more attempts left",
-        request_url,
-        retry_count - iteration
-      );
-      tokio::time::sleep(wait_time).await;
-
-      continue;
     }
+    Err(error @ ScrapeError::Disallowed { .. }) => return Err(error),
+    Err(error) => error,
+  };
+
+  if error.is_host_failure() {
+    circuit_breaker.record_failure(request_url);
+  } else {
+    circuit_breaker.record_success(request_url);
   }
 
-  Err(anyhow!(
-    "Failed to get a response from `{:?}` after {:?} tries.",
-    request_url,
-    retry_count,
-  ))
+  Err(error)
+}
+
+/// Checks the URL against the host's robots.txt, unless the client ignores it, then sends a single attempt at it.
+///
+/// Failing to get the robots.txt fails the attempt, so it's retried and counted against the host like any other failure.
+async fn send_request(
+  client: &HttpClient,
+  request_url: &str,
+  headers: &HeaderMap,
+  rate_limiter: &DeviationRateLimiter,
+) -> ScrapeResult<Response> {
+  if let Some(robots) = client.robots() {
+    robots.check(client, rate_limiter, request_url).await?;
+  }
+
+  rate_limiter.wait_for(request_url).await;
+
+  match client
+    .get(request_url)
+    .headers(headers.clone())
+    .send()
+    .await
+  {
+    Ok(response)
+      if response.status().is_success() || response.status() == StatusCode::NOT_MODIFIED =>
+    {
+      Ok(response)
+    }
+    Ok(response) => Err(ScrapeError::HttpStatus {
+      url: request_url.to_string(),
+      status: response.status(),
+    }),
+    Err(error) => Err(ScrapeError::network(request_url, error)),
+  }
+}
+
+/// Downloads the whole body from the desired URL, retrying if either the request or reading the body fails.
+///
+/// Both kinds of failure count against the same `retry_count` attempts.
+/// Returns the body, along with the size the response said it'd be.
+/// Bodies are read through [`HttpClient::read_body`], so stalled transfers are retried too.
+/// `before_body` is handed the response's Content-Length before the body is read, and can refuse it.
+///
+/// # Errors
+/// - Everything [`get_with_retry`] and `before_body` return.
+/// - [`ScrapeError::Stalled`] The body kept stalling after the desired amount of attempts.
+pub async fn get_bytes_with_retry(
+  client: &HttpClient,
+  request_url: String,
+  retry_count: usize,
+  rate_limiter: &DeviationRateLimiter,
+  wait_time: Duration,
+  before_body: impl Fn(Option<u64>) -> ScrapeResult<()>,
+) -> ScrapeResult<(Bytes, Option<u64>)> {
+  let mut last_error = None;
+
+  for iteration in 1..=retry_count {
+    let error = match attempt_request(client, &request_url, &HeaderMap::new(), rate_limiter).await {
+      Ok(response) => {
+        let expected_size = response.content_length();
+        before_body(expected_size)?;
+
+        match client.read_body(response).await {
+          Ok(bytes) => return Ok((bytes, expected_size)),
+          Err(error) => {
+            if error.is_host_failure() {
+              client.circuit_breaker().record_failure(&request_url);
+            }
+
+            error
+          }
+        }
+      }
+      Err(error) => error,
+    };
+
+    if !error.is_retryable() {
+      return Err(error);
+    }
+
+    tracing::warn!(
+      "Failed to download {:?}. {} more attempts left. Reason: `{error}`",
+      request_url,
+      retry_count - iteration
+    );
+    last_error = Some(error);
+    tokio::time::sleep(wait_time).await;
+  }
+
+  Err(last_error.unwrap_or_else(|| ScrapeError::Config("The retry count can't be 0.".to_string())))
 }
@@ -1,78 +1,396 @@
-use crate::get_with_retry;
-use crate::{ratelimiter::DeviationRateLimiter, BANNED_URL_LIST};
-use crate::{DATA_DESTINATION_DIR, REQUEST_RETRY_COUNT, RETRY_REQUEST_WAIT_DURATION};
-use reqwest::Client;
+use crate::download_plan::PlannedItem;
+use crate::error::{ScrapeError, ScrapeResult};
+use crate::events::ScrapeEvent;
+use crate::helper_methods::get_bytes_with_retry;
+use crate::metadata::{Deduplication, MediaMetadata};
+use crate::path_template::{sanitize_component, TemplateValues};
+use crate::progress::Stat;
+use crate::scrape_context::ScrapeContext;
+use crate::storage::Storage;
+use crate::verification::{quarantine_media, Verification};
+use crate::{MEDIA_VERIFICATION_ATTEMPTS, REQUEST_RETRY_COUNT, RETRY_REQUEST_WAIT_DURATION};
+use base64::Engine;
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
 use scraper::ElementRef;
+use serde::{Deserialize, Serialize};
 use std::fs;
 use std::io::Write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MediaData {
   pub url: String,
   pub extension: String,
+  /// The name the file was uploaded with, without its extension.
+  pub original_name: Option<String>,
+  /// The hex encoded MD5 of the file as advertised by the archive.
+  pub md5: Option<String>,
+  /// The approximate size in bytes as displayed by the archive.
+  pub advertised_size: Option<u64>,
+  /// The width and height as displayed by the archive.
+  pub dimensions: Option<(u32, u32)>,
+}
+
+/// Who made a post, as shown in its header.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PosterDetails {
+  pub name: Option<String>,
+  pub tripcode: Option<String>,
+  /// The per-thread poster ID, without the leading `ID:`.
+  pub poster_id: Option<String>,
+}
+
+/// Identifies the post that media and hyperlinks were taken from.
+#[derive(Debug, Clone)]
+pub struct PostContext<'a> {
+  pub board: &'a str,
+  pub thread_id: &'a str,
+  pub post_id: &'a str,
+  pub timestamp: Option<DateTime<Utc>>,
+}
+
+impl PostContext<'_> {
+  pub fn template_values(&self) -> TemplateValues<'_> {
+    TemplateValues {
+      board: self.board,
+      thread_id: self.thread_id,
+      post_id: self.post_id,
+      timestamp: self.timestamp,
+      ..Default::default()
+    }
+  }
+}
+
+/// Media bytes after being checked by [`Verification`].
+enum FetchedMedia {
+  Accepted {
+    bytes: Bytes,
+    verification: Verification,
+  },
+  /// Every attempt failed verification.
+  Rejected {
+    /// Where the last failed attempt was quarantined, if it could be.
+    quarantine_path: Option<PathBuf>,
+    verification: Verification,
+  },
 }
 
 impl MediaData {
+  #[tracing::instrument(name = "media", skip_all, fields(url = %self.url))]
   pub async fn download(
     self,
-    client: &Client,
-    rate_limiter: &DeviationRateLimiter,
-    thread_id: &str,
-    post_id: &str,
+    context: &ScrapeContext,
+    post: &PostContext<'_>,
     file_appender: &str,
-  ) -> anyhow::Result<()> {
-    let MediaData {
-      url: media_url,
-      extension: media_extension,
-    } = self;
+  ) -> ScrapeResult<()> {
+    tracing::info!("Found a media URL.");
 
-    tracing::info!("{thread_id}-{post_id}: Found a media URL.",);
+    let media_file_path = self.target_path(&context.storage, post, file_appender)?;
 
-    let media_file_path = format!(
-      "{}/{}/{}-{}-{}.{}",
-      DATA_DESTINATION_DIR, thread_id, thread_id, post_id, file_appender, media_extension,
-    );
-    let media_file_path = Path::new(&media_file_path);
+    if let Some(download_plan) = &context.download_plan {
+      let existing_path = if media_file_path.exists() {
+        Some(media_file_path.clone())
+      } else {
+        self
+          .md5
+          .as_deref()
+          .and_then(|md5| context.media_index.get(md5))
+      };
+
+      download_plan.add(PlannedItem::Media {
+        thread_id: post.thread_id.to_string(),
+        post_id: post.post_id.to_string(),
+        url: self.url,
+        target_path: media_file_path,
+        estimated_size: self.advertised_size,
+        existing_path,
+      });
+
+      return Ok(());
+    }
 
     if media_file_path.exists() {
-      tracing::info!("{thread_id}-{post_id}: media file already exists.");
+      tracing::info!("media file already exists.");
+      context.run_stats.increment(Stat::MediaSkipped);
       return Ok(());
     }
 
-    if let Some(media_thread_path) = Path::new(&media_file_path).parent() {
+    if let Some(media_thread_path) = media_file_path.parent() {
       if !media_thread_path.exists() {
-        tracing::info!("{thread_id}: Creating directories for media");
+        tracing::info!("Creating directories for media");
         fs::create_dir_all(media_thread_path)?;
       }
     }
 
-    tracing::info!("{thread_id}-{post_id}: Grabbing media URL `{media_url:?}`",);
-    let response = get_with_retry(
-      client,
-      media_url,
-      REQUEST_RETRY_COUNT,
-      rate_limiter,
-      RETRY_REQUEST_WAIT_DURATION,
-    )
-    .await?;
-    let response_bytes = response.bytes().await?;
-
-    tracing::info!("{thread_id}-{post_id}: Obtaining write handle on files.");
+    if let Some(existing_path) = self
+      .md5
+      .as_deref()
+      .and_then(|md5| context.media_index.get(md5))
+    {
+      tracing::info!("Media was already downloaded to `{existing_path:?}`, skipping download.");
+      context.run_stats.increment(Stat::MediaSkipped);
+
+      return self.save_duplicate(context, post, media_file_path, existing_path);
+    }
+
+    let fetched_media = tokio::select! {
+      fetched_media = self.fetch_verified_bytes(context, post, &media_file_path) => fetched_media?,
+      _ = context.shutdown.aborted() => {
+        return Err(ScrapeError::Aborted { url: self.url });
+      }
+    };
+
+    let (response_bytes, verification) = match fetched_media {
+      FetchedMedia::Accepted {
+        bytes,
+        verification,
+      } => (bytes, verification),
+      FetchedMedia::Rejected {
+        quarantine_path,
+        verification,
+      } => {
+        let error = ScrapeError::VerificationFailed {
+          url: self.url.clone(),
+          attempts: MEDIA_VERIFICATION_ATTEMPTS,
+          verification: verification.clone(),
+        };
+
+        if let Some(quarantine_path) = quarantine_path {
+          self.write_metadata(context, post, quarantine_path, None, Some(verification));
+        }
+
+        return Err(error);
+      }
+    };
+
+    // Content-Length isn't always sent, so the size is checked again now that it's known.
+    context.check_disk_space(response_bytes.len() as u64)?;
+
+    // Media is written to a `.part` file first, so a stopped run never leaves a truncated file at the real path.
+    let part_file_path = part_file_path(&media_file_path);
+
+    tracing::info!("Obtaining write handle on files.");
     let mut sorted_file = fs::OpenOptions::new()
       .write(true)
       .create(true)
       .truncate(true)
-      .open(media_file_path)?;
+      .open(&part_file_path)?;
 
-    tracing::info!("{thread_id}-{post_id}: Writing media bytes to file.");
+    tracing::info!("Writing media bytes to file.");
     sorted_file.write_all(&response_bytes)?;
 
+    if let Some(timestamp) = post.timestamp {
+      sorted_file.set_modified(SystemTime::from(timestamp))?;
+    }
+
+    drop(sorted_file);
+    fs::rename(&part_file_path, &media_file_path)?;
+    context
+      .disk_guard
+      .record_written(response_bytes.len() as u64);
+
+    if let Some(md5) = &self.md5 {
+      if let Err(error) = context.media_index.insert(md5, &media_file_path) {
+        tracing::error!("Failed to add media to the index. Reason: `{error:?}`");
+      }
+    }
+
+    context.run_stats.increment(Stat::MediaDownloaded);
+    context
+      .run_stats
+      .add(Stat::BytesDownloaded, response_bytes.len() as u64);
+    context.events.send(ScrapeEvent::MediaSaved {
+      thread_id: post.thread_id.to_string(),
+      post_id: post.post_id.to_string(),
+      path: media_file_path.clone(),
+    });
+    self.write_metadata(context, post, media_file_path, None, Some(verification));
+
+    Ok(())
+  }
+
+  /// Where the media is saved to for the given post.
+  ///
+  /// # Errors
+  /// - The media path template couldn't be rendered.
+  pub fn target_path(
+    &self,
+    storage: &Storage,
+    post: &PostContext<'_>,
+    file_appender: &str,
+  ) -> ScrapeResult<PathBuf> {
+    storage.media_path(&TemplateValues {
+      appender: file_appender,
+      extension: Some(&self.extension),
+      original_name: self.original_name.as_deref(),
+      md5: self.md5.as_deref(),
+      ..post.template_values()
+    })
+  }
+
+  /// Downloads the media, checking it against the advertised MD5 and size.
+  ///
+  /// Media that fails verification is quarantined and requested again, up to [`MEDIA_VERIFICATION_ATTEMPTS`] times.
+  ///
+  /// # Errors
+  /// - The media couldn't be requested.
+  async fn fetch_verified_bytes(
+    &self,
+    context: &ScrapeContext,
+    post: &PostContext<'_>,
+    media_file_path: &Path,
+  ) -> ScrapeResult<FetchedMedia> {
+    let PostContext {
+      thread_id, post_id, ..
+    } = post;
+    let file_name = media_file_path
+      .file_name()
+      .map(|file_name| file_name.to_string_lossy().to_string())
+      .unwrap_or_else(|| format!("{thread_id}-{post_id}.{}", self.extension));
+    let mut last_verification = None;
+    let mut last_quarantine_path = None;
+
+    for attempt in 1..=MEDIA_VERIFICATION_ATTEMPTS {
+      tracing::info!("Grabbing media URL `{:?}`", self.url);
+      let (response_bytes, expected_size) = get_bytes_with_retry(
+        &context.client,
+        self.url.clone(),
+        REQUEST_RETRY_COUNT,
+        &context.rate_limiter,
+        RETRY_REQUEST_WAIT_DURATION,
+        |expected_size| context.check_disk_space(expected_size.unwrap_or(0)),
+      )
+      .await?;
+
+      let verification = Verification::check(&response_bytes, self.md5.as_deref(), expected_size);
+
+      if !verification.is_mismatched() {
+        return Ok(FetchedMedia::Accepted {
+          bytes: response_bytes,
+          verification,
+        });
+      }
+
+      tracing::warn!(
+        "Media failed verification on attempt {attempt}/{MEDIA_VERIFICATION_ATTEMPTS}. {verification:?}"
+      );
+
+      let quarantine_name =
+        sanitize_component(&format!("{thread_id}-{post_id}-{attempt}-{file_name}"));
+
+      match quarantine_media(
+        &context.storage.quarantine_dir(),
+        &response_bytes,
+        &quarantine_name,
+      ) {
+        Ok(quarantine_path) => last_quarantine_path = Some(quarantine_path),
+        Err(error) => {
+          tracing::error!("Failed to quarantine media. Reason: `{error:?}`")
+        }
+      }
+
+      last_verification = Some(verification);
+    }
+
+    Ok(FetchedMedia::Rejected {
+      quarantine_path: last_quarantine_path,
+      verification: last_verification.unwrap_or(Verification::Unverified),
+    })
+  }
+
+  /// Hard links the already downloaded copy of this media to where it would've been saved,
+  /// falling back to only recording a reference to it if linking isn't possible.
+  fn save_duplicate(
+    self,
+    context: &ScrapeContext,
+    post: &PostContext<'_>,
+    media_file_path: PathBuf,
+    existing_path: PathBuf,
+  ) -> ScrapeResult<()> {
+    let deduplication = if !context.storage.hard_link_duplicate_media {
+      Deduplication::Referenced {
+        original: existing_path,
+      }
+    } else if let Err(error) = fs::hard_link(&existing_path, &media_file_path) {
+      tracing::warn!(
+        "Failed to hard link `{existing_path:?}`, recording a reference instead. Reason: `{error:?}`"
+      );
+
+      Deduplication::Referenced {
+        original: existing_path,
+      }
+    } else {
+      Deduplication::HardLinked {
+        original: existing_path,
+      }
+    };
+
+    let saved_path = match &deduplication {
+      Deduplication::Referenced { original } => original.clone(),
+      Deduplication::HardLinked { .. } => media_file_path.clone(),
+    };
+    context.events.send(ScrapeEvent::MediaSaved {
+      thread_id: post.thread_id.to_string(),
+      post_id: post.post_id.to_string(),
+      path: saved_path,
+    });
+    self.write_metadata(context, post, media_file_path, Some(deduplication), None);
+
     Ok(())
   }
+
+  fn write_metadata(
+    self,
+    context: &ScrapeContext,
+    post: &PostContext<'_>,
+    media_file_path: PathBuf,
+    deduplication: Option<Deduplication>,
+    verification: Option<Verification>,
+  ) {
+    let PostContext {
+      thread_id, post_id, ..
+    } = post;
+    let saved_path = match &deduplication {
+      Some(Deduplication::Referenced { original }) => original.clone(),
+      _ => media_file_path,
+    };
+
+    let metadata = MediaMetadata {
+      board: post.board.to_string(),
+      thread_id: thread_id.to_string(),
+      post_id: post_id.to_string(),
+      posted_at: post.timestamp,
+      url: self.url,
+      original_filename: self
+        .original_name
+        .map(|original_name| format!("{}.{}", original_name, self.extension)),
+      md5: self.md5,
+      saved_path,
+      deduplication,
+      verification,
+    };
+
+    if let Err(error) = metadata.append_to_disk(&context.storage, post) {
+      tracing::error!("Failed to write media metadata. Reason: `{error:?}`");
+    }
+  }
+}
+
+/// The path media is written to while it's being downloaded.
+pub fn part_file_path(media_file_path: &Path) -> PathBuf {
+  let mut part_file_path = media_file_path.as_os_str().to_owned();
+  part_file_path.push(".part");
+
+  PathBuf::from(part_file_path)
 }
 
-pub fn extract_hyperlinks_from_post(post: &ElementRef) -> Option<Vec<String>> {
+/// Hyperlinks containing any of the banned URLs are left out.
+pub fn extract_hyperlinks_from_post(
+  post: &ElementRef,
+  banned_urls: &[String],
+) -> Option<Vec<String>> {
   let mut hyperlinks = vec![];
 
   let post_wrapper_element = find_child_with_class(post, "post_wrapper")?;
@@ -90,9 +408,9 @@ pub fn extract_hyperlinks_from_post(post: &ElementRef) -> Option<Vec<String>> {
       continue;
     };
 
-    if BANNED_URL_LIST
+    if banned_urls
       .iter()
-      .any(|banned_url| hyperlink.to_lowercase().contains(banned_url))
+      .any(|banned_url| hyperlink.to_lowercase().contains(banned_url.as_str()))
     {
       continue;
     }
@@ -120,14 +438,145 @@ pub fn extract_media_url_from_post(post: &ElementRef) -> Option<MediaData> {
   let media_url = post_file_filename_value.attr("href").map(str::to_string)?;
   let media_name = post_file_filename_value.attr("title").map(str::to_string)?;
 
-  let media_extension = media_name.split('.').last().map(str::to_string)?;
+  let (original_name, media_extension) = match media_name.rsplit_once('.') {
+    Some((original_name, extension)) => (Some(original_name.to_string()), extension.to_string()),
+    None => (None, media_name),
+  };
+
+  let (advertised_size, dimensions) =
+    find_child_with_class(&post_file_element, "post_file_metadata")
+      .map(|metadata_element| parse_file_metadata(&metadata_element.text().collect::<String>()))
+      .unwrap_or_default();
 
   Some(MediaData {
     url: media_url,
     extension: media_extension,
+    original_name,
+    md5: extract_media_md5_from_post(&post_wrapper_element),
+    advertised_size,
+    dimensions,
   })
 }
 
+/// Parses the archive's file metadata text, such as `1.15 MiB, 1920x1080`, into a size in bytes and dimensions.
+fn parse_file_metadata(metadata: &str) -> (Option<u64>, Option<(u32, u32)>) {
+  let mut size = None;
+  let mut dimensions = None;
+
+  for part in metadata.split(',').map(str::trim) {
+    if let Some((width, height)) = part.split_once('x') {
+      if let (Ok(width), Ok(height)) = (width.trim().parse(), height.trim().parse()) {
+        dimensions = Some((width, height));
+        continue;
+      }
+    }
+
+    let unit_start = part
+      .find(|character: char| character.is_ascii_alphabetic())
+      .unwrap_or(part.len());
+    let (value, unit) = part.split_at(unit_start);
+    let Ok(value) = value.trim().parse::<f64>() else {
+      continue;
+    };
+    let multiplier: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+      "b" => 1,
+      "kib" | "kb" => 1 << 10,
+      "mib" | "mb" => 1 << 20,
+      "gib" | "gb" => 1 << 30,
+      _ => continue,
+    };
+
+    size = Some((value * multiplier as f64) as u64);
+  }
+
+  (size, dimensions)
+}
+
+/// Reads the base64 `data-md5` attribute off of the post's thumbnail, and returns it hex encoded.
+fn extract_media_md5_from_post(post_wrapper_element: &ElementRef) -> Option<String> {
+  let image_box_element = find_child_with_class(post_wrapper_element, "thread_image_box")?;
+  let image_link_element = find_child_with_class(&image_box_element, "thread_image_link")?;
+  let image_element = find_child_with_tag(&image_link_element, "img")?;
+
+  let encoded_md5 = image_element.value().attr("data-md5")?;
+  let md5 = base64::engine::general_purpose::STANDARD
+    .decode(encoded_md5)
+    .ok()?;
+
+  Some(md5.iter().map(|byte| format!("{byte:02x}")).collect())
+}
+
+/// Reads the time a post was made from the `datetime` attribute in its header.
+pub fn extract_post_timestamp(post: &ElementRef) -> Option<DateTime<Utc>> {
+  let post_wrapper_element = find_child_with_class(post, "post_wrapper")?;
+  let header_element = find_child_with_tag(&post_wrapper_element, "header")?;
+  let post_data_element = find_child_with_class(&header_element, "post_data")?;
+  let time_wrap_element = find_child_with_class(&post_data_element, "time_wrap")?;
+  let time_element = find_child_with_tag(&time_wrap_element, "time")?;
+
+  let datetime = time_element.value().attr("datetime")?;
+
+  DateTime::parse_from_rfc3339(datetime)
+    .ok()
+    .map(|datetime| datetime.with_timezone(&Utc))
+}
+
+/// Who made the post, with everything missing from its header left as `None`.
+pub fn extract_poster_details(post: &ElementRef) -> PosterDetails {
+  let Some(post_wrapper_element) = find_child_with_class(post, "post_wrapper") else {
+    return PosterDetails::default();
+  };
+  let Some(header_element) = find_child_with_tag(&post_wrapper_element, "header") else {
+    return PosterDetails::default();
+  };
+  let Some(post_data_element) = find_child_with_class(&header_element, "post_data") else {
+    return PosterDetails::default();
+  };
+  let Some(poster_data_element) = find_child_with_class(&post_data_element, "post_poster_data")
+  else {
+    return PosterDetails::default();
+  };
+  let child_text = |class| {
+    find_child_with_class(&poster_data_element, class)
+      .map(|element| element.text().collect::<String>().trim().to_string())
+      .filter(|text| !text.is_empty())
+  };
+
+  PosterDetails {
+    name: child_text("post_author"),
+    tripcode: child_text("post_tripcode"),
+    poster_id: child_text("poster_hash")
+      .map(|poster_id| poster_id.trim_start_matches("ID:").to_string()),
+  }
+}
+
+/// The comment's text with its markup stripped.
+pub fn extract_post_text(post: &ElementRef) -> Option<String> {
+  let post_wrapper_element = find_child_with_class(post, "post_wrapper")?;
+  let text_element = find_child_with_class(&post_wrapper_element, "text")?;
+
+  Some(text_element.text().collect::<String>().trim().to_string())
+}
+
+/// The IDs of the posts the comment quotes with `>>`.
+pub fn extract_reply_targets(post: &ElementRef) -> Option<Vec<String>> {
+  let post_wrapper_element = find_child_with_class(post, "post_wrapper")?;
+  let text_element = find_child_with_class(&post_wrapper_element, "text")?;
+
+  Some(
+    text_element
+      .descendants()
+      .filter_map(ElementRef::wrap)
+      .filter(|element| {
+        element
+          .value()
+          .has_class("backlink", scraper::CaseSensitivity::CaseSensitive)
+      })
+      .filter_map(|backlink| backlink.value().attr("data-post").map(str::to_string))
+      .collect(),
+  )
+}
+
 pub fn find_child_with_class<'a>(element: &'a ElementRef, class: &str) -> Option<ElementRef<'a>> {
   element.child_elements().find(|child| {
     child
@@ -135,3 +584,9 @@ pub fn find_child_with_class<'a>(element: &'a ElementRef, class: &str) -> Option
       .has_class(class, scraper::CaseSensitivity::CaseSensitive)
   })
 }
+
+pub fn find_child_with_tag<'a>(element: &'a ElementRef, tag: &str) -> Option<ElementRef<'a>> {
+  element
+    .child_elements()
+    .find(|child| child.value().name() == tag)
+}
@@ -0,0 +1,170 @@
+use crate::error::ScrapeResult;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// An item kept in a [`JsonLinesQueue`].
+pub trait QueueItem: Serialize + DeserializeOwned {
+  /// Whether both are entries for the same item, of which only the latest is kept.
+  fn is_same_item(&self, other: &Self) -> bool;
+}
+
+/// Items kept across runs as a JSON line file.
+///
+/// Pushed items are appended to the file straight away, so a run that dies partway through doesn't lose them.
+/// [`save`](Self::save) then rewrites the file with only what's still queued.
+#[derive(Debug, Clone)]
+pub struct JsonLinesQueue<T> {
+  queue_file_path: PathBuf,
+  append_on_push: bool,
+  queue: Arc<Mutex<Vec<T>>>,
+}
+
+impl<T: QueueItem> JsonLinesQueue<T> {
+  /// Loads the items queued by previous runs, starting an empty queue if there are none.
+  ///
+  /// Lines that fail to parse are skipped, and an item appended more than once is only kept as its latest entry.
+  ///
+  /// # Errors
+  /// - The queue file exists but couldn't be read.
+  pub fn load<P: AsRef<Path>>(queue_file_path: P) -> ScrapeResult<Self> {
+    let queue_file_path = queue_file_path.as_ref().to_path_buf();
+    let mut queue: Vec<T> = vec![];
+
+    if queue_file_path.exists() {
+      let queue_file = BufReader::new(fs::File::open(&queue_file_path)?);
+
+      for line in queue_file.lines() {
+        let line = line?;
+
+        match serde_json::from_str::<T>(&line) {
+          Ok(item) => {
+            queue.retain(|queued_item| !queued_item.is_same_item(&item));
+            queue.push(item);
+          }
+          Err(error) => {
+            tracing::warn!(
+              "Skipping an invalid entry {line:?} in {queue_file_path:?}. Reason: `{error:?}`"
+            );
+          }
+        }
+      }
+
+      tracing::info!(
+        "Loaded {} items queued by previous runs from {queue_file_path:?}.",
+        queue.len()
+      );
+    }
+
+    Ok(Self {
+      queue_file_path,
+      append_on_push: true,
+      queue: Arc::new(Mutex::new(queue)),
+    })
+  }
+
+  /// Keeps pushed items in memory only, for runs that don't write anything.
+  pub fn without_appending(mut self) -> Self {
+    self.append_on_push = false;
+
+    self
+  }
+
+  pub fn push(&self, item: T) {
+    let mut queue = self.queue.lock().unwrap();
+
+    if self.append_on_push {
+      if let Err(error) = self.append(&item) {
+        tracing::error!(
+          "Failed to append to {:?}, the item is only saved at the end of the run. Reason: `{error}`",
+          self.queue_file_path
+        );
+      }
+    }
+
+    queue.push(item);
+  }
+
+  /// Pushes the item, replacing any earlier entry for the same item.
+  pub fn replace(&self, item: T) {
+    self
+      .queue
+      .lock()
+      .unwrap()
+      .retain(|queued_item| !queued_item.is_same_item(&item));
+    self.push(item);
+  }
+
+  /// Empties the queue, returning everything that was in it.
+  pub fn take(&self) -> Vec<T> {
+    std::mem::take(&mut self.queue.lock().unwrap())
+  }
+
+  /// Removes and returns the items matching the predicate.
+  pub fn take_where(&self, predicate: impl Fn(&T) -> bool) -> Vec<T> {
+    let mut queue = self.queue.lock().unwrap();
+    let (matching, rest) = std::mem::take(&mut *queue).into_iter().partition(predicate);
+    *queue = rest;
+
+    matching
+  }
+
+  pub fn len(&self) -> usize {
+    self.queue.lock().unwrap().len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  /// Writes every item still queued, removing the file if there are none.
+  ///
+  /// # Errors
+  /// - The queue file couldn't be written or removed.
+  pub fn save(&self) -> ScrapeResult<()> {
+    let queue = self.queue.lock().unwrap();
+
+    if queue.is_empty() {
+      if self.queue_file_path.exists() {
+        fs::remove_file(&self.queue_file_path)?;
+      }
+
+      return Ok(());
+    }
+
+    self.create_parent_dirs()?;
+
+    let mut queue_file = fs::File::create(&self.queue_file_path)?;
+
+    for item in queue.iter() {
+      writeln!(queue_file, "{}", serde_json::to_string(item)?)?;
+    }
+
+    Ok(())
+  }
+
+  fn append(&self, item: &T) -> ScrapeResult<()> {
+    self.create_parent_dirs()?;
+
+    let mut queue_file = fs::OpenOptions::new()
+      .append(true)
+      .create(true)
+      .open(&self.queue_file_path)?;
+    writeln!(queue_file, "{}", serde_json::to_string(item)?)?;
+
+    Ok(())
+  }
+
+  fn create_parent_dirs(&self) -> ScrapeResult<()> {
+    if let Some(queue_parent_dirs) = self.queue_file_path.parent() {
+      if !queue_parent_dirs.exists() {
+        fs::create_dir_all(queue_parent_dirs)?;
+      }
+    }
+
+    Ok(())
+  }
+}
@@ -0,0 +1,76 @@
+use std::time::Duration;
+
+pub use crate::archive_scraper::{Scraper, ScraperBuilder};
+pub use crate::error::{ScrapeError, ScrapeResult};
+pub use crate::events::ScrapeEvent;
+
+pub mod archive_scraper;
+pub mod backend;
+pub mod bandwidth;
+pub mod circuit_breaker;
+pub mod deferred_media;
+pub mod disk_guard;
+pub mod download_plan;
+pub mod error;
+pub mod events;
+pub mod failed_items;
+pub mod helper_methods;
+pub mod html_parsing;
+pub mod http_client;
+pub mod json_lines_queue;
+pub mod media_filter;
+pub mod media_index;
+pub mod metadata;
+pub mod page_cache;
+pub mod path_template;
+pub mod post_filter;
+pub mod profile;
+pub mod progress;
+pub mod ratelimiter;
+pub mod robots;
+pub mod scrape;
+pub mod scrape_context;
+pub mod scrape_state;
+pub mod shutdown;
+pub mod snapshot;
+pub mod storage;
+pub mod thread_list;
+pub mod verification;
+
+pub const DEFAULT_BANNED_URL_LIST: &[&str] = &[
+  "x.",
+  "twitter.",
+  "youtube.",
+  "twitch.",
+  "youtu.",
+  "wikipedia.",
+  "steampowered.",
+  "amiami.",
+  "gov.",
+  "gitlab.",
+  "github.",
+  "fandom.",
+  "poal.",
+  "spanix",
+  "pixiv.",
+  "amazon.",
+  "gamersupps.",
+  "nexusmods.",
+  "speedrun.",
+  "yle.",
+  "amazon.",
+];
+pub const MAX_REQUEST_RATE_LIMIT: u64 = 4;
+pub const BASE_RATE_LIMIT_DURATION: Duration = Duration::new(0, 143_240_219);
+pub const RETRY_REQUEST_WAIT_DURATION: Duration = Duration::new(0, 51_230_508);
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+pub const DEFAULT_READ_TIMEOUT: Duration = Duration::from_secs(60);
+/// How long a media transfer is measured over before it's checked against the minimum throughput.
+pub const DEFAULT_STALL_WINDOW: Duration = Duration::from_secs(30);
+/// How many failures in a row it takes for a host to be treated as down.
+pub const DEFAULT_CIRCUIT_FAILURE_THRESHOLD: u32 = 5;
+/// How long requests to a host that's down are held off before it's probed again.
+pub const DEFAULT_CIRCUIT_PROBE_INTERVAL: Duration = Duration::from_secs(60);
+pub const REQUEST_RETRY_COUNT: usize = 5;
+/// How many times media is downloaded before giving up on it failing verification.
+pub const MEDIA_VERIFICATION_ATTEMPTS: usize = 3;
@@ -0,0 +1,107 @@
+use crate::html_parsing::MediaData;
+use serde::Deserialize;
+use std::fmt;
+
+/// Rules deciding which media gets downloaded, checked before any request for it is made.
+///
+/// Size and resolution come from the archive's post metadata, media without them always passes those checks.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MediaFilter {
+  /// Extensions to allow, such as `webm`. Everything is allowed if this and `mime_types` are both empty.
+  pub extensions: Vec<String>,
+  /// MIME types to allow, guessed from the extension. Accepts wildcards such as `video/*`.
+  pub mime_types: Vec<String>,
+  /// The minimum size in bytes.
+  pub min_size: Option<u64>,
+  /// The maximum size in bytes.
+  pub max_size: Option<u64>,
+  pub min_width: Option<u32>,
+  pub min_height: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum FilterRejection {
+  Type,
+  TooSmall,
+  TooLarge,
+  LowResolution,
+  /// The rest are from [`PostFilter`](crate::post_filter::PostFilter), and count posts rather than media.
+  PostText,
+  Poster,
+  PostDate,
+  NotAReply,
+}
+
+impl MediaFilter {
+  /// # Errors
+  /// - The media broke one of the rules.
+  pub fn check(&self, media: &MediaData) -> Result<(), FilterRejection> {
+    if !self.allows_type(&media.extension) {
+      return Err(FilterRejection::Type);
+    }
+
+    if let Some(size) = media.advertised_size {
+      if self.min_size.is_some_and(|min_size| size < min_size) {
+        return Err(FilterRejection::TooSmall);
+      }
+
+      if self.max_size.is_some_and(|max_size| size > max_size) {
+        return Err(FilterRejection::TooLarge);
+      }
+    }
+
+    if let Some((width, height)) = media.dimensions {
+      if self.min_width.is_some_and(|min_width| width < min_width)
+        || self
+          .min_height
+          .is_some_and(|min_height| height < min_height)
+      {
+        return Err(FilterRejection::LowResolution);
+      }
+    }
+
+    Ok(())
+  }
+
+  fn allows_type(&self, extension: &str) -> bool {
+    if self.extensions.is_empty() && self.mime_types.is_empty() {
+      return true;
+    }
+
+    if self.extensions.iter().any(|allowed| {
+      allowed
+        .trim_start_matches('.')
+        .eq_ignore_ascii_case(extension)
+    }) {
+      return true;
+    }
+
+    mime_guess::from_ext(extension).iter().any(|mime| {
+      self
+        .mime_types
+        .iter()
+        .any(|allowed| match allowed.split_once('/') {
+          Some((allowed_type, "*")) => mime.type_().as_str().eq_ignore_ascii_case(allowed_type),
+          _ => mime.essence_str().eq_ignore_ascii_case(allowed),
+        })
+    })
+  }
+}
+
+impl fmt::Display for FilterRejection {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let reason = match self {
+      FilterRejection::Type => "type not allowed",
+      FilterRejection::TooSmall => "too small",
+      FilterRejection::TooLarge => "too large",
+      FilterRejection::LowResolution => "resolution too low",
+      FilterRejection::PostText => "comment didn't match",
+      FilterRejection::Poster => "poster not allowed",
+      FilterRejection::PostDate => "posted outside the date range",
+      FilterRejection::NotAReply => "not a reply to a watched post",
+    };
+
+    write!(f, "{reason}")
+  }
+}
@@ -0,0 +1,95 @@
+use crate::error::ScrapeResult;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// An index of every downloaded media file keyed by its MD5, used to avoid downloading the same file twice.
+///
+/// The index is kept as a JSON line file, with new entries appended as media gets downloaded.
+#[derive(Debug, Clone)]
+pub struct MediaIndex {
+  index_file_path: PathBuf,
+  entries: Arc<Mutex<HashMap<String, PathBuf>>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct MediaIndexEntry {
+  md5: String,
+  path: PathBuf,
+}
+
+impl MediaIndex {
+  /// Loads the index at the given path, starting an empty one if it doesn't exist yet.
+  ///
+  /// Lines that fail to parse are skipped.
+  ///
+  /// # Errors
+  /// - The index file exists but couldn't be read.
+  pub fn load<P: AsRef<Path>>(index_file_path: P) -> ScrapeResult<Self> {
+    let index_file_path = index_file_path.as_ref().to_path_buf();
+    let mut entries = HashMap::new();
+
+    if index_file_path.exists() {
+      let index_file = BufReader::new(fs::File::open(&index_file_path)?);
+
+      for line in index_file.lines() {
+        let line = line?;
+
+        match serde_json::from_str::<MediaIndexEntry>(&line) {
+          Ok(entry) => {
+            entries.insert(entry.md5, entry.path);
+          }
+          Err(error) => {
+            tracing::warn!("Skipping an invalid media index entry {line:?}. Reason: `{error:?}`");
+          }
+        }
+      }
+    }
+
+    tracing::info!("Loaded {} entries from the media index.", entries.len());
+
+    Ok(Self {
+      index_file_path,
+      entries: Arc::new(Mutex::new(entries)),
+    })
+  }
+
+  /// Returns where the media with the given MD5 was saved, if it was and the file still exists.
+  pub fn get(&self, md5: &str) -> Option<PathBuf> {
+    let entries = self.entries.lock().unwrap();
+
+    entries.get(md5).filter(|path| path.exists()).cloned()
+  }
+
+  /// Records where the media with the given MD5 was saved.
+  ///
+  /// # Errors
+  /// - The entry couldn't be appended to the index file.
+  pub fn insert(&self, md5: &str, path: &Path) -> ScrapeResult<()> {
+    let mut entries = self.entries.lock().unwrap();
+
+    if let Some(index_parent_dirs) = self.index_file_path.parent() {
+      if !index_parent_dirs.exists() {
+        fs::create_dir_all(index_parent_dirs)?;
+      }
+    }
+
+    let mut index_file = fs::OpenOptions::new()
+      .append(true)
+      .create(true)
+      .truncate(false)
+      .open(&self.index_file_path)?;
+    let entry = MediaIndexEntry {
+      md5: md5.to_string(),
+      path: path.to_path_buf(),
+    };
+
+    writeln!(index_file, "{}", serde_json::to_string(&entry)?)?;
+    entries.insert(entry.md5, entry.path);
+
+    Ok(())
+  }
+}
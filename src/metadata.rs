@@ -0,0 +1,64 @@
+use crate::error::ScrapeResult;
+use crate::html_parsing::PostContext;
+use crate::storage::Storage;
+use crate::verification::Verification;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// A record of a saved media file, appended as a JSON line to the file at [`Storage::metadata_path`].
+#[derive(Debug, Clone, Serialize)]
+pub struct MediaMetadata {
+  pub board: String,
+  pub thread_id: String,
+  pub post_id: String,
+  pub posted_at: Option<DateTime<Utc>>,
+  pub url: String,
+  /// The filename the media was uploaded with, extension included.
+  pub original_filename: Option<String>,
+  pub md5: Option<String>,
+  pub saved_path: PathBuf,
+  /// Set when the media had already been downloaded for another post.
+  pub deduplication: Option<Deduplication>,
+  /// How the downloaded bytes compared to what was advertised. Not set for deduplicated media.
+  pub verification: Option<Verification>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Deduplication {
+  /// The saved path is a hard link to the original.
+  HardLinked { original: PathBuf },
+  /// Nothing was saved for this post, the original is where the media can be found.
+  Referenced { original: PathBuf },
+}
+
+impl MediaMetadata {
+  /// Appends this record to the metadata file for the post it came from.
+  ///
+  /// # Errors
+  /// - The metadata path template couldn't be rendered for the post.
+  /// - The metadata file couldn't be created or written to.
+  pub fn append_to_disk(&self, storage: &Storage, post: &PostContext<'_>) -> ScrapeResult<()> {
+    let file_path = storage.metadata_path(&post.template_values())?;
+
+    if let Some(metadata_parent_dirs) = file_path.parent() {
+      if !metadata_parent_dirs.exists() {
+        tracing::info!("Creating dir for metadata");
+        fs::create_dir_all(metadata_parent_dirs)?;
+      }
+    }
+
+    let mut metadata_file = fs::OpenOptions::new()
+      .append(true)
+      .create(true)
+      .truncate(false)
+      .open(&file_path)?;
+
+    writeln!(metadata_file, "{}", serde_json::to_string(self)?)?;
+
+    Ok(())
+  }
+}
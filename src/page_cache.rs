@@ -0,0 +1,123 @@
+use crate::error::ScrapeResult;
+use chrono::{DateTime, Utc};
+use md5::{Digest, Md5};
+use reqwest::header::{HeaderMap, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+/// The validators of search and thread pages from previous runs, kept so unchanged pages can be revalidated instead of downloaded again.
+///
+/// Each page's validators are kept as a JSON file named after the MD5 of its URL.
+/// The page itself is only kept as its snapshot, which an unchanged page is read back from.
+#[derive(Debug, Clone)]
+pub struct PageCache {
+  cache_dir: PathBuf,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedPage {
+  pub url: String,
+  pub etag: Option<String>,
+  pub last_modified: Option<String>,
+  pub cached_at: DateTime<Utc>,
+}
+
+impl PageCache {
+  pub fn new(cache_dir: impl Into<PathBuf>) -> Self {
+    Self {
+      cache_dir: cache_dir.into(),
+    }
+  }
+
+  /// The cached copy of the page, if there's one that can be revalidated.
+  ///
+  /// Entries that can't be read are treated as missing.
+  pub fn get(&self, url: &str) -> Option<CachedPage> {
+    match self.try_get(url) {
+      Ok(cached_page) => cached_page,
+      Err(error) => {
+        tracing::warn!("Ignoring an unreadable cache entry for `{url}`. Reason: `{error}`");
+
+        None
+      }
+    }
+  }
+
+  fn try_get(&self, url: &str) -> ScrapeResult<Option<CachedPage>> {
+    let entry_path = self.entry_path(url);
+
+    if !entry_path.exists() {
+      return Ok(None);
+    }
+
+    let cached_page: CachedPage = serde_json::from_str(&fs::read_to_string(entry_path)?)?;
+
+    // Guards against the unlikely case of two URLs hashing the same.
+    Ok(Some(cached_page).filter(|cached_page| cached_page.url == url))
+  }
+
+  /// Stores the page's validators if the response can be revalidated later, replacing any older ones.
+  ///
+  /// # Errors
+  /// - The entry couldn't be written.
+  pub fn store(&self, url: &str, response_headers: &HeaderMap) -> ScrapeResult<()> {
+    let header = |name| {
+      response_headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+    };
+    let etag = header(ETAG);
+    let last_modified = header(LAST_MODIFIED);
+
+    if etag.is_none() && last_modified.is_none() {
+      return Ok(());
+    }
+
+    if !self.cache_dir.exists() {
+      fs::create_dir_all(&self.cache_dir)?;
+    }
+
+    let cached_page = CachedPage {
+      url: url.to_string(),
+      etag,
+      last_modified,
+      cached_at: Utc::now(),
+    };
+
+    fs::write(self.entry_path(url), serde_json::to_string(&cached_page)?)?;
+
+    Ok(())
+  }
+
+  fn entry_path(&self, url: &str) -> PathBuf {
+    let url_hash: String = Md5::digest(url.as_bytes())
+      .iter()
+      .map(|byte| format!("{byte:02x}"))
+      .collect();
+
+    self.cache_dir.join(format!("{url_hash}.json"))
+  }
+}
+
+impl CachedPage {
+  /// Headers asking the archive to only send the page again if it changed.
+  pub fn conditional_headers(&self) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+
+    if let Some(etag) = self.etag.as_deref().and_then(|etag| etag.parse().ok()) {
+      headers.insert(IF_NONE_MATCH, etag);
+    }
+
+    if let Some(last_modified) = self
+      .last_modified
+      .as_deref()
+      .and_then(|last_modified| last_modified.parse().ok())
+    {
+      headers.insert(IF_MODIFIED_SINCE, last_modified);
+    }
+
+    headers
+  }
+}
@@ -0,0 +1,301 @@
+use crate::error::{ScrapeError, ScrapeResult};
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Utc};
+use std::path::PathBuf;
+
+/// The longest any single path component is allowed to be, in bytes.
+///
+/// Most filesystems cap names at 255 bytes, some headroom is left for `.part` style suffixes.
+pub const MAX_PATH_COMPONENT_LENGTH: usize = 200;
+/// Extensions longer than this are treated as part of the name when truncating.
+const MAX_PRESERVED_EXTENSION_LENGTH: usize = 16;
+const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// A path template such as `{board}/{thread}/{post}_{original_name}.{ext}`.
+///
+/// Available fields:
+/// - `{board}`
+/// - `{thread}`
+/// - `{post}`
+/// - `{appender}`
+/// - `{ext}`
+/// - `{original_name}` The uploaded filename without its extension.
+/// - `{md5}` The hex encoded MD5 of the media as advertised by the archive.
+/// - `{date}` or `{date:<strftime format>}` The time the post was made, in UTC. Slashes in the format create directories.
+///
+/// Substituted values are sanitized so they can't escape their path component,
+/// and every component of the rendered path is limited to [`MAX_PATH_COMPONENT_LENGTH`] bytes.
+#[derive(Debug, Clone)]
+pub struct PathTemplate {
+  segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone)]
+enum Segment {
+  Literal(String),
+  Field(Field),
+  Date(String),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Field {
+  Board,
+  Thread,
+  Post,
+  Appender,
+  Extension,
+  OriginalName,
+  Md5,
+}
+
+/// The values a [`PathTemplate`] can be rendered with.
+///
+/// Fields that don't apply to what's being rendered are left as `None`,
+/// and will fail the render if the template asks for them.
+#[derive(Debug, Clone, Default)]
+pub struct TemplateValues<'a> {
+  pub board: &'a str,
+  pub thread_id: &'a str,
+  pub post_id: &'a str,
+  pub appender: &'a str,
+  pub extension: Option<&'a str>,
+  pub original_name: Option<&'a str>,
+  pub md5: Option<&'a str>,
+  pub timestamp: Option<DateTime<Utc>>,
+}
+
+impl PathTemplate {
+  /// # Errors
+  /// - The template contains an unknown field.
+  /// - A `{` is never closed, or a `}` is never opened.
+  /// - A date format is invalid.
+  pub fn parse(template: &str) -> ScrapeResult<Self> {
+    let mut segments = vec![];
+    let mut literal = String::new();
+    let mut characters = template.chars();
+
+    while let Some(character) = characters.next() {
+      match character {
+        '{' => {
+          let mut placeholder = String::new();
+          let mut closed = false;
+
+          for character in characters.by_ref() {
+            if character == '}' {
+              closed = true;
+              break;
+            }
+
+            placeholder.push(character);
+          }
+
+          if !closed {
+            return Err(ScrapeError::Config(format!(
+              "Unclosed `{{` in path template `{template}`."
+            )));
+          }
+
+          if !literal.is_empty() {
+            segments.push(Segment::Literal(std::mem::take(&mut literal)));
+          }
+
+          segments.push(Segment::from_placeholder(&placeholder)?);
+        }
+        '}' => {
+          return Err(ScrapeError::Config(format!(
+            "Unopened `}}` in path template `{template}`."
+          )));
+        }
+        _ => literal.push(character),
+      }
+    }
+
+    if !literal.is_empty() {
+      segments.push(Segment::Literal(literal));
+    }
+
+    Ok(Self { segments })
+  }
+
+  /// Renders the template into a relative path.
+  ///
+  /// # Errors
+  /// - The template uses a field that has no value in `values`.
+  /// - The rendered path is empty.
+  pub fn render(&self, values: &TemplateValues) -> ScrapeResult<PathBuf> {
+    let mut rendered = String::new();
+
+    for segment in &self.segments {
+      match segment {
+        Segment::Literal(text) => rendered.push_str(text),
+        Segment::Field(field) => rendered.push_str(&sanitize_component(field.value(values)?)),
+        Segment::Date(format) => {
+          let Some(timestamp) = values.timestamp else {
+            return Err(ScrapeError::Config(
+              "Path template uses `date`, but no post time is known.".to_string(),
+            ));
+          };
+          let formatted = timestamp.format(format).to_string();
+          let formatted: Vec<String> = formatted.split('/').map(sanitize_component).collect();
+
+          rendered.push_str(&formatted.join("/"));
+        }
+      }
+    }
+
+    let path: PathBuf = rendered
+      .split('/')
+      .filter(|component| !component.is_empty())
+      .map(truncate_component)
+      .collect();
+
+    if path.as_os_str().is_empty() {
+      return Err(ScrapeError::Config(
+        "Path template rendered an empty path.".to_string(),
+      ));
+    }
+
+    Ok(path)
+  }
+
+  /// The directory part of the template, if it only depends on the board and thread.
+  ///
+  /// e.g. `{board}` for `{board}/{date}/{post}.{ext}`, or `None` for `{thread}-{post}.{ext}`.
+  pub fn thread_dir(&self) -> Option<PathTemplate> {
+    let mut segments = vec![];
+    let mut thread_dir = None;
+
+    for segment in &self.segments {
+      match segment {
+        Segment::Literal(text) => {
+          if let Some((dir_text, _)) = text.rsplit_once('/') {
+            let mut dir_segments = segments.clone();
+
+            if !dir_text.is_empty() {
+              dir_segments.push(Segment::Literal(dir_text.to_string()));
+            }
+
+            thread_dir = Some(PathTemplate {
+              segments: dir_segments,
+            })
+            .filter(|dir| !dir.segments.is_empty());
+          }
+
+          segments.push(segment.clone());
+        }
+        Segment::Field(Field::Board | Field::Thread) => segments.push(segment.clone()),
+        // Everything from here on depends on the post.
+        Segment::Field(_) | Segment::Date(_) => break,
+      }
+    }
+
+    thread_dir
+  }
+}
+
+impl Segment {
+  fn from_placeholder(placeholder: &str) -> ScrapeResult<Self> {
+    let (name, format) = match placeholder.split_once(':') {
+      Some((name, format)) => (name.trim(), Some(format)),
+      None => (placeholder.trim(), None),
+    };
+
+    if name == "date" {
+      let format = format.unwrap_or(DEFAULT_DATE_FORMAT);
+
+      if StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+        return Err(ScrapeError::Config(format!(
+          "Invalid date format `{format}` in path template."
+        )));
+      }
+
+      return Ok(Segment::Date(format.to_string()));
+    }
+
+    if format.is_some() {
+      return Err(ScrapeError::Config(format!(
+        "Path template field `{name}` doesn't take a format."
+      )));
+    }
+
+    let field = match name {
+      "board" => Field::Board,
+      "thread" => Field::Thread,
+      "post" => Field::Post,
+      "appender" => Field::Appender,
+      "ext" => Field::Extension,
+      "original_name" => Field::OriginalName,
+      "md5" => Field::Md5,
+      _ => {
+        return Err(ScrapeError::Config(format!(
+          "Unknown path template field `{name}`."
+        )))
+      }
+    };
+
+    Ok(Segment::Field(field))
+  }
+}
+
+impl Field {
+  fn value<'a>(&self, values: &TemplateValues<'a>) -> ScrapeResult<&'a str> {
+    let (name, value) = match self {
+      Field::Board => return Ok(values.board),
+      Field::Thread => return Ok(values.thread_id),
+      Field::Post => return Ok(values.post_id),
+      Field::Appender => return Ok(values.appender),
+      Field::Extension => ("ext", values.extension),
+      Field::OriginalName => ("original_name", values.original_name),
+      Field::Md5 => ("md5", values.md5),
+    };
+
+    value.ok_or_else(|| {
+      ScrapeError::Config(format!(
+        "Path template uses `{name}`, but it has no value here."
+      ))
+    })
+  }
+}
+
+/// Replaces anything that isn't safe to have in a single path component.
+pub fn sanitize_component(value: &str) -> String {
+  let sanitized: String = value
+    .chars()
+    .map(|character| match character {
+      '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+      character if character.is_control() => '_',
+      character => character,
+    })
+    .collect();
+  let sanitized = sanitized.trim_end_matches(['.', ' ']);
+
+  match sanitized {
+    "" if value.is_empty() => String::new(),
+    "" | "." | ".." => "_".to_string(),
+    sanitized => sanitized.to_string(),
+  }
+}
+
+/// Shortens a path component to [`MAX_PATH_COMPONENT_LENGTH`] bytes, keeping the extension if it has one.
+fn truncate_component(component: &str) -> String {
+  if component.len() <= MAX_PATH_COMPONENT_LENGTH {
+    return component.to_string();
+  }
+
+  let (stem, extension) = match component.rsplit_once('.') {
+    Some((stem, extension))
+      if !stem.is_empty() && extension.len() <= MAX_PRESERVED_EXTENSION_LENGTH =>
+    {
+      (stem, format!(".{extension}"))
+    }
+    _ => (component, String::new()),
+  };
+
+  let mut stem_length = MAX_PATH_COMPONENT_LENGTH - extension.len();
+
+  while !stem.is_char_boundary(stem_length) {
+    stem_length -= 1;
+  }
+
+  format!("{}{}", &stem[..stem_length], extension)
+}
@@ -0,0 +1,134 @@
+use crate::html_parsing::{
+  extract_post_text, extract_post_timestamp, extract_poster_details, extract_reply_targets,
+};
+use crate::media_filter::FilterRejection;
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use scraper::ElementRef;
+use serde::Deserialize;
+
+/// Rules deciding which posts have their media and hyperlinks collected, checked before anything is extracted from a post.
+///
+/// Every rule that's set has to pass, and a rule with several values passes if any of them match.
+/// Posts without a timestamp always pass the date range.
+///
+/// example:
+/// ```toml
+/// [post_filter]
+/// text_patterns = ["(?i)\\bkaraoke\\b"]
+/// keywords = ["unarchived"]
+/// tripcodes = ["!!cL1pPeRtRp"]
+/// posted_after = "2024-03-01T00:00:00Z"
+/// replies_to = ["70000001"]
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(try_from = "PostFilterSettings")]
+pub struct PostFilter {
+  /// Regular expressions matched against the comment's text.
+  pub text_patterns: Vec<Regex>,
+  /// Matched case insensitively anywhere in the comment's text, alongside `text_patterns`.
+  pub keywords: Vec<String>,
+  pub names: Vec<String>,
+  pub tripcodes: Vec<String>,
+  /// Per-thread poster IDs, without the leading `ID:`.
+  pub poster_ids: Vec<String>,
+  pub posted_after: Option<DateTime<Utc>>,
+  pub posted_before: Option<DateTime<Utc>>,
+  /// Only posts quoting one of these post IDs pass.
+  pub replies_to: Vec<String>,
+}
+
+/// [`PostFilter`] as written in a profile, before its patterns are compiled.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct PostFilterSettings {
+  text_patterns: Vec<String>,
+  keywords: Vec<String>,
+  names: Vec<String>,
+  tripcodes: Vec<String>,
+  poster_ids: Vec<String>,
+  posted_after: Option<DateTime<Utc>>,
+  posted_before: Option<DateTime<Utc>>,
+  replies_to: Vec<String>,
+}
+
+impl PostFilter {
+  /// # Errors
+  /// - The post broke one of the rules.
+  pub fn check(&self, post: &ElementRef) -> Result<(), FilterRejection> {
+    if !self.names.is_empty() || !self.tripcodes.is_empty() || !self.poster_ids.is_empty() {
+      let poster = extract_poster_details(post);
+
+      if !any_listed(&self.names, poster.name.as_deref())
+        || !any_listed(&self.tripcodes, poster.tripcode.as_deref())
+        || !any_listed(&self.poster_ids, poster.poster_id.as_deref())
+      {
+        return Err(FilterRejection::Poster);
+      }
+    }
+
+    if self.posted_after.is_some() || self.posted_before.is_some() {
+      if let Some(timestamp) = extract_post_timestamp(post) {
+        if self.posted_after.is_some_and(|after| timestamp < after)
+          || self.posted_before.is_some_and(|before| timestamp > before)
+        {
+          return Err(FilterRejection::PostDate);
+        }
+      }
+    }
+
+    if !self.replies_to.is_empty()
+      && !extract_reply_targets(post)
+        .unwrap_or_default()
+        .iter()
+        .any(|reply_target| self.replies_to.contains(reply_target))
+    {
+      return Err(FilterRejection::NotAReply);
+    }
+
+    if !self.text_patterns.is_empty() || !self.keywords.is_empty() {
+      let post_text = extract_post_text(post).unwrap_or_default();
+      let lowercase_text = post_text.to_lowercase();
+
+      if !self
+        .text_patterns
+        .iter()
+        .any(|pattern| pattern.is_match(&post_text))
+        && !self
+          .keywords
+          .iter()
+          .any(|keyword| lowercase_text.contains(&keyword.to_lowercase()))
+      {
+        return Err(FilterRejection::PostText);
+      }
+    }
+
+    Ok(())
+  }
+}
+
+impl TryFrom<PostFilterSettings> for PostFilter {
+  type Error = regex::Error;
+
+  fn try_from(settings: PostFilterSettings) -> Result<Self, Self::Error> {
+    Ok(Self {
+      text_patterns: settings
+        .text_patterns
+        .iter()
+        .map(|pattern| Regex::new(pattern))
+        .collect::<Result<_, _>>()?,
+      keywords: settings.keywords,
+      names: settings.names,
+      tripcodes: settings.tripcodes,
+      poster_ids: settings.poster_ids,
+      posted_after: settings.posted_after,
+      posted_before: settings.posted_before,
+      replies_to: settings.replies_to,
+    })
+  }
+}
+
+/// Whether the value is one of the allowed ones, where an empty list allows anything.
+fn any_listed(allowed: &[String], value: Option<&str>) -> bool {
+  allowed.is_empty() || value.is_some_and(|value| allowed.iter().any(|allowed| allowed == value))
+}
@@ -0,0 +1,109 @@
+use crate::bandwidth::BandwidthSettings;
+use crate::disk_guard::DiskSettings;
+use crate::error::{ScrapeError, ScrapeResult};
+use crate::http_client::HttpSettings;
+use crate::media_filter::MediaFilter;
+use crate::post_filter::PostFilter;
+use crate::storage::Storage;
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tokio::task::JoinHandle;
+
+/// How often a watched profile is checked for changes.
+const PROFILE_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Settings for a scrape, loaded from a TOML file.
+///
+/// Every section is optional, anything left out keeps its default.
+///
+/// example:
+/// ```toml
+/// [media_filter]
+/// mime_types = ["video/*", "audio/*"]
+/// min_size = 10240
+///
+/// [post_filter]
+/// keywords = ["karaoke"]
+///
+/// [http]
+/// proxy = "socks5h://127.0.0.1:9050"
+///
+/// [bandwidth]
+/// max_bytes_per_sec = 1048576
+///
+/// [storage]
+/// media_path_template = "{md5}.{ext}"
+///
+/// [disk]
+/// min_free_bytes = 10_000_000_000
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+  pub media_filter: MediaFilter,
+  pub post_filter: PostFilter,
+  pub http: HttpSettings,
+  pub storage: Storage,
+  pub disk: DiskSettings,
+  /// The only section that's picked up again when a watched profile changes, see [`Profile::watch`].
+  pub bandwidth: BandwidthSettings,
+}
+
+impl Profile {
+  /// # Errors
+  /// - The file couldn't be read.
+  /// - The file isn't a valid profile.
+  pub fn load<P: AsRef<Path>>(profile_path: P) -> ScrapeResult<Self> {
+    let profile_path = profile_path.as_ref();
+    let profile_contents = fs::read_to_string(profile_path)?;
+
+    tracing::info!("Loading profile {profile_path:?}");
+
+    toml::from_str(&profile_contents).map_err(|error| {
+      ScrapeError::Config(format!(
+        "Invalid profile {profile_path:?}. Reason: `{error}`"
+      ))
+    })
+  }
+
+  /// Reloads the profile in the background whenever the file changes, handing every new version to `on_change`.
+  ///
+  /// Changes that fail to load are logged and skipped.
+  pub fn watch<F>(profile_path: impl Into<PathBuf>, on_change: F) -> JoinHandle<()>
+  where
+    F: Fn(Profile) + Send + 'static,
+  {
+    let profile_path = profile_path.into();
+
+    tokio::spawn(async move {
+      let mut last_modified = modified_at(&profile_path);
+
+      loop {
+        tokio::time::sleep(PROFILE_POLL_INTERVAL).await;
+
+        let modified = modified_at(&profile_path);
+
+        if modified == last_modified {
+          continue;
+        }
+
+        last_modified = modified;
+
+        match Profile::load(&profile_path) {
+          Ok(profile) => on_change(profile),
+          Err(error) => {
+            tracing::error!("Failed to reload the profile, keeping the last one. Reason: `{error}`")
+          }
+        }
+      }
+    })
+  }
+}
+
+fn modified_at(profile_path: &Path) -> Option<SystemTime> {
+  fs::metadata(profile_path)
+    .and_then(|metadata| metadata.modified())
+    .ok()
+}
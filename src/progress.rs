@@ -0,0 +1,314 @@
+use crate::error::ScrapeResult;
+use crate::media_filter::FilterRejection;
+use chrono::{DateTime, Utc};
+use indicatif::{HumanBytes, ProgressBar, ProgressStyle};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+
+const PROGRESS_REFRESH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Counters for everything a run has done, shared between every stage of the scrape.
+#[derive(Debug, Clone)]
+pub struct RunStats {
+  started_at: DateTime<Utc>,
+  start_instant: Instant,
+  counters: Arc<Counters>,
+  filter_rejections: Arc<Mutex<BTreeMap<FilterRejection, u64>>>,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+  pages_total: AtomicU64,
+  pages_done: AtomicU64,
+  pages_failed: AtomicU64,
+  threads_queued: AtomicU64,
+  threads_done: AtomicU64,
+  threads_failed: AtomicU64,
+  media_queued: AtomicU64,
+  media_downloaded: AtomicU64,
+  media_skipped: AtomicU64,
+  media_failed: AtomicU64,
+  media_deferred: AtomicU64,
+  bytes_downloaded: AtomicU64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Stat {
+  PagesTotal,
+  PageDone,
+  PageFailed,
+  ThreadQueued,
+  ThreadDone,
+  ThreadFailed,
+  MediaQueued,
+  MediaDownloaded,
+  /// Media that was already on disk, was deduplicated, or was only extracted by a replay.
+  MediaSkipped,
+  MediaFailed,
+  /// Media left for a later run, as its host was still down.
+  MediaDeferred,
+  BytesDownloaded,
+}
+
+/// A snapshot of [`RunStats`], written out as JSON at the end of a run.
+#[derive(Debug, Clone, Serialize)]
+pub struct RunSummary {
+  pub started_at: DateTime<Utc>,
+  pub finished_at: DateTime<Utc>,
+  pub elapsed_seconds: f64,
+  pub pages: StageSummary,
+  pub threads: StageSummary,
+  pub media: MediaSummary,
+  pub bytes_downloaded: u64,
+  pub filter_rejections: BTreeMap<String, u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StageSummary {
+  pub total: u64,
+  pub done: u64,
+  pub failed: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MediaSummary {
+  pub queued: u64,
+  pub downloaded: u64,
+  pub skipped: u64,
+  pub failed: u64,
+  pub deferred: u64,
+  pub filtered: u64,
+}
+
+/// A live status line on the terminal, refreshed in the background until finished.
+pub struct ProgressDisplay {
+  progress_bar: ProgressBar,
+  refresh_task: JoinHandle<()>,
+}
+
+impl RunStats {
+  pub fn new() -> Self {
+    Self {
+      started_at: Utc::now(),
+      start_instant: Instant::now(),
+      counters: Arc::new(Counters::default()),
+      filter_rejections: Arc::default(),
+    }
+  }
+
+  pub fn add(&self, stat: Stat, amount: u64) {
+    self.counter(stat).fetch_add(amount, Ordering::Relaxed);
+  }
+
+  pub fn increment(&self, stat: Stat) {
+    self.add(stat, 1);
+  }
+
+  pub fn get(&self, stat: Stat) -> u64 {
+    self.counter(stat).load(Ordering::Relaxed)
+  }
+
+  pub fn record_filter_rejection(&self, rejection: FilterRejection) {
+    let mut filter_rejections = self.filter_rejections.lock().unwrap();
+
+    *filter_rejections.entry(rejection).or_insert(0) += 1;
+  }
+
+  pub fn summary(&self) -> RunSummary {
+    let filter_rejections: BTreeMap<String, u64> = self
+      .filter_rejections
+      .lock()
+      .unwrap()
+      .iter()
+      .map(|(rejection, count)| (rejection.to_string(), *count))
+      .collect();
+
+    RunSummary {
+      started_at: self.started_at,
+      finished_at: Utc::now(),
+      elapsed_seconds: self.start_instant.elapsed().as_secs_f64(),
+      pages: StageSummary {
+        total: self.get(Stat::PagesTotal),
+        done: self.get(Stat::PageDone),
+        failed: self.get(Stat::PageFailed),
+      },
+      threads: StageSummary {
+        total: self.get(Stat::ThreadQueued),
+        done: self.get(Stat::ThreadDone),
+        failed: self.get(Stat::ThreadFailed),
+      },
+      media: MediaSummary {
+        queued: self.get(Stat::MediaQueued),
+        downloaded: self.get(Stat::MediaDownloaded),
+        skipped: self.get(Stat::MediaSkipped),
+        failed: self.get(Stat::MediaFailed),
+        deferred: self.get(Stat::MediaDeferred),
+        filtered: filter_rejections.values().sum(),
+      },
+      bytes_downloaded: self.get(Stat::BytesDownloaded),
+      filter_rejections,
+    }
+  }
+
+  fn counter(&self, stat: Stat) -> &AtomicU64 {
+    let counters = &self.counters;
+
+    match stat {
+      Stat::PagesTotal => &counters.pages_total,
+      Stat::PageDone => &counters.pages_done,
+      Stat::PageFailed => &counters.pages_failed,
+      Stat::ThreadQueued => &counters.threads_queued,
+      Stat::ThreadDone => &counters.threads_done,
+      Stat::ThreadFailed => &counters.threads_failed,
+      Stat::MediaQueued => &counters.media_queued,
+      Stat::MediaDownloaded => &counters.media_downloaded,
+      Stat::MediaSkipped => &counters.media_skipped,
+      Stat::MediaFailed => &counters.media_failed,
+      Stat::MediaDeferred => &counters.media_deferred,
+      Stat::BytesDownloaded => &counters.bytes_downloaded,
+    }
+  }
+}
+
+impl Default for RunStats {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl RunSummary {
+  /// Renders the summary as a table for the terminal.
+  pub fn to_table(&self) -> String {
+    let mut rows = vec![
+      (
+        "Elapsed".to_string(),
+        format!("{:.1}s", self.elapsed_seconds),
+      ),
+      (
+        "Pages".to_string(),
+        format!(
+          "{}/{} done, {} failed",
+          self.pages.done, self.pages.total, self.pages.failed
+        ),
+      ),
+      (
+        "Threads".to_string(),
+        format!(
+          "{}/{} done, {} failed",
+          self.threads.done, self.threads.total, self.threads.failed
+        ),
+      ),
+      (
+        "Media".to_string(),
+        format!(
+          "{} queued, {} downloaded, {} skipped, {} failed, {} deferred, {} filtered",
+          self.media.queued,
+          self.media.downloaded,
+          self.media.skipped,
+          self.media.failed,
+          self.media.deferred,
+          self.media.filtered
+        ),
+      ),
+      (
+        "Downloaded".to_string(),
+        HumanBytes(self.bytes_downloaded).to_string(),
+      ),
+    ];
+
+    rows.extend(
+      self
+        .filter_rejections
+        .iter()
+        .map(|(rejection, count)| (format!("Filtered ({rejection})"), count.to_string())),
+    );
+
+    let label_width = rows.iter().map(|(label, _)| label.len()).max().unwrap_or(0);
+
+    rows
+      .into_iter()
+      .map(|(label, value)| format!("{label:<label_width$} | {value}"))
+      .collect::<Vec<String>>()
+      .join("\n")
+  }
+
+  /// Writes the summary as JSON into the given directory, named after when the run started.
+  ///
+  /// # Errors
+  /// - The directory couldn't be created, or the file couldn't be written.
+  pub fn write_json<P: AsRef<Path>>(&self, directory: P) -> ScrapeResult<PathBuf> {
+    let directory = directory.as_ref();
+
+    if !directory.exists() {
+      fs::create_dir_all(directory)?;
+    }
+
+    let summary_path = directory.join(format!(
+      "run_summary-{}.json",
+      self.started_at.format("%Y%m%d-%H%M%S")
+    ));
+    fs::write(&summary_path, serde_json::to_string_pretty(self)?)?;
+
+    Ok(summary_path)
+  }
+}
+
+impl ProgressDisplay {
+  /// Starts redrawing the progress line from the given stats.
+  pub fn start(run_stats: RunStats) -> Self {
+    let progress_bar = ProgressBar::new_spinner();
+    progress_bar
+      .set_style(ProgressStyle::with_template("{spinner} [{elapsed_precise}] {msg}").unwrap());
+
+    let refresh_task = tokio::spawn({
+      let progress_bar = progress_bar.clone();
+
+      async move {
+        let mut last_bytes = 0;
+        let mut last_refresh = Instant::now();
+
+        loop {
+          tokio::time::sleep(PROGRESS_REFRESH_INTERVAL).await;
+
+          let bytes = run_stats.get(Stat::BytesDownloaded);
+          let elapsed = last_refresh.elapsed().as_secs_f64();
+          let rate = ((bytes - last_bytes) as f64 / elapsed.max(f64::EPSILON)) as u64;
+          last_bytes = bytes;
+          last_refresh = Instant::now();
+
+          progress_bar.set_message(format!(
+            "pages {}/{} ({} failed) | threads {}/{} ({} failed) | media {} queued, {} done, {} failed | {} @ {}/s",
+            run_stats.get(Stat::PageDone),
+            run_stats.get(Stat::PagesTotal),
+            run_stats.get(Stat::PageFailed),
+            run_stats.get(Stat::ThreadDone),
+            run_stats.get(Stat::ThreadQueued),
+            run_stats.get(Stat::ThreadFailed),
+            run_stats.get(Stat::MediaQueued),
+            run_stats.get(Stat::MediaDownloaded) + run_stats.get(Stat::MediaSkipped),
+            run_stats.get(Stat::MediaFailed),
+            HumanBytes(bytes),
+            HumanBytes(rate),
+          ));
+          progress_bar.tick();
+        }
+      }
+    });
+
+    Self {
+      progress_bar,
+      refresh_task,
+    }
+  }
+
+  pub fn finish(self) {
+    self.refresh_task.abort();
+    self.progress_bar.finish_and_clear();
+  }
+}
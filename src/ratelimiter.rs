@@ -1,32 +1,86 @@
+use crate::circuit_breaker::host_of;
+use crate::error::{ScrapeError, ScrapeResult};
 use rand::prelude::*;
 use ratelimit::Ratelimiter;
+use std::collections::HashMap;
+use std::time::Instant;
 use std::{sync::Arc, time::Duration};
 use tokio::sync::Mutex;
 
 #[derive(Clone)]
 pub struct DeviationRateLimiter {
-  rng: Arc<Mutex<ThreadRng>>,
+  rng: Arc<Mutex<StdRng>>,
   rate_limiter: Arc<Ratelimiter>,
+  /// Hosts that want requests spaced out further than the global rate limit, e.g. from a `Crawl-delay`.
+  host_delays: Arc<std::sync::Mutex<HashMap<String, HostDelay>>>,
+}
+
+struct HostDelay {
+  delay: Duration,
+  next_request_at: Instant,
 }
 
 impl DeviationRateLimiter {
   /// The maximum range of deviation in nanoseconds.
   const DEVIATION: u64 = 236_857_093;
 
-  pub fn new() -> anyhow::Result<Self> {
+  /// # Errors
+  /// - The rate limit settings are invalid.
+  pub fn new() -> ScrapeResult<Self> {
     let rate_limiter = Ratelimiter::builder(
       crate::MAX_REQUEST_RATE_LIMIT,
       crate::BASE_RATE_LIMIT_DURATION,
     )
     .max_tokens(4)
-    .build()?;
+    .build()
+    .map_err(|error| ScrapeError::Config(format!("Invalid rate limit. Reason: `{error:?}`")))?;
 
     Ok(Self {
-      rng: Arc::new(Mutex::new(rand::thread_rng())),
+      rng: Arc::new(Mutex::new(StdRng::from_entropy())),
       rate_limiter: Arc::new(rate_limiter),
+      host_delays: Arc::default(),
     })
   }
 
+  /// Spaces out requests to the URL's host by at least the delay, on top of the global rate limit.
+  pub fn set_host_delay(&self, url: &str, delay: Duration) {
+    let Some(host) = host_of(url) else {
+      return;
+    };
+
+    self
+      .host_delays
+      .lock()
+      .unwrap()
+      .entry(host)
+      .and_modify(|host_delay| host_delay.delay = delay)
+      .or_insert(HostDelay {
+        delay,
+        next_request_at: Instant::now(),
+      });
+  }
+
+  /// Waits on the global rate limit, and then on the URL's host if it has a delay set.
+  pub async fn wait_for(&self, url: &str) {
+    self.wait().await;
+
+    let Some(host) = host_of(url) else {
+      return;
+    };
+    let request_at = {
+      let mut host_delays = self.host_delays.lock().unwrap();
+      let Some(host_delay) = host_delays.get_mut(&host) else {
+        return;
+      };
+      let request_at = host_delay.next_request_at.max(Instant::now());
+      host_delay.next_request_at = request_at + host_delay.delay;
+
+      request_at
+    };
+
+    tokio::time::sleep_until(request_at.into()).await;
+  }
+
   pub async fn wait(&self) {
     let deviation = self.get_deviation().await;
 
@@ -0,0 +1,134 @@
+use crate::circuit_breaker::host_of;
+use crate::error::{ScrapeError, ScrapeResult};
+use crate::http_client::HttpClient;
+use crate::ratelimiter::DeviationRateLimiter;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use texting_robots::{get_robots_url, Robot};
+use tokio::sync::OnceCell;
+
+/// The name rules in a robots.txt are matched against, falling back to the rules for `*`.
+pub const DEFAULT_ROBOTS_USER_AGENT: &str = "thread-archive-scraper";
+
+/// Every host's robots.txt, fetched the first time a request is sent to the host and kept for the rest of the run.
+///
+/// A robots.txt that couldn't be gotten, from a server error or the host being unreachable, fails the request instead
+/// so the failure counts towards the host's circuit, and it's fetched again on the next attempt.
+/// A `Crawl-delay` is handed to the rate limiter for that host.
+/// Requests to a host wait on its robots.txt being fetched, without holding up requests to other hosts.
+#[derive(Debug, Clone)]
+pub struct RobotsCache {
+  user_agent: String,
+  policies: Arc<Mutex<HashMap<String, Arc<OnceCell<RobotsPolicy>>>>>,
+}
+
+#[derive(Debug)]
+enum RobotsPolicy {
+  /// The host has no robots.txt, or it's invalid.
+  AllowAll,
+  Rules(Robot),
+}
+
+impl RobotsCache {
+  pub fn new(user_agent: impl Into<String>) -> Self {
+    Self {
+      user_agent: user_agent.into(),
+      policies: Arc::default(),
+    }
+  }
+
+  /// # Errors
+  /// - [`ScrapeError::Disallowed`] The host's robots.txt disallows the URL.
+  /// - [`ScrapeError::Network`] The robots.txt couldn't be gotten.
+  /// - [`ScrapeError::HttpStatus`] The robots.txt request got a server error.
+  pub async fn check(
+    &self,
+    client: &HttpClient,
+    rate_limiter: &DeviationRateLimiter,
+    url: &str,
+  ) -> ScrapeResult<()> {
+    let Some(host) = host_of(url) else {
+      return Ok(());
+    };
+    let policy_cell = self
+      .policies
+      .lock()
+      .unwrap()
+      .entry(host)
+      .or_default()
+      .clone();
+    let policy = policy_cell
+      .get_or_try_init(|| self.fetch_policy(client, rate_limiter, url))
+      .await?;
+
+    match policy {
+      RobotsPolicy::Rules(robot) if !robot.allowed(url) => Err(ScrapeError::Disallowed {
+        url: url.to_string(),
+      }),
+      _ => Ok(()),
+    }
+  }
+
+  /// A missing robots.txt, one refused with any other client error, or an invalid one allows everything.
+  ///
+  /// # Errors
+  /// - The robots.txt couldn't be gotten, which isn't kept so it's tried again.
+  async fn fetch_policy(
+    &self,
+    client: &HttpClient,
+    rate_limiter: &DeviationRateLimiter,
+    url: &str,
+  ) -> ScrapeResult<RobotsPolicy> {
+    let Ok(robots_url) = get_robots_url(url) else {
+      return Ok(RobotsPolicy::AllowAll);
+    };
+
+    rate_limiter.wait_for(&robots_url).await;
+
+    let response = match client.get(&robots_url).send().await {
+      Ok(response) if response.status().is_success() => response,
+      Ok(response) if response.status().is_client_error() => {
+        tracing::info!(
+          "No robots.txt at `{robots_url}`, status {}.",
+          response.status()
+        );
+
+        return Ok(RobotsPolicy::AllowAll);
+      }
+      Ok(response) => {
+        return Err(ScrapeError::HttpStatus {
+          url: robots_url,
+          status: response.status(),
+        })
+      }
+      Err(error) => return Err(ScrapeError::network(robots_url, error)),
+    };
+
+    let robots_txt = response
+      .bytes()
+      .await
+      .map_err(|error| ScrapeError::network(&robots_url, error))?;
+
+    match Robot::new(&self.user_agent, &robots_txt) {
+      Ok(robot) => {
+        if let Some(crawl_delay) = robot
+          .delay
+          .filter(|delay| delay.is_finite() && *delay > 0.0)
+        {
+          tracing::info!("`{robots_url}` asks for a crawl delay of {crawl_delay}s.");
+          rate_limiter.set_host_delay(url, Duration::from_secs_f32(crawl_delay));
+        }
+
+        Ok(RobotsPolicy::Rules(robot))
+      }
+      Err(error) => {
+        tracing::warn!(
+          "Invalid robots.txt at `{robots_url}`, treating everything as allowed. Reason: `{error}`"
+        );
+
+        Ok(RobotsPolicy::AllowAll)
+      }
+    }
+  }
+}
@@ -0,0 +1,908 @@
+use crate::circuit_breaker::host_of;
+use crate::deferred_media::DeferredMedia;
+use crate::download_plan::PlannedItem;
+use crate::error::{ScrapeError, ScrapeResult};
+use crate::events::ScrapeEvent;
+use crate::failed_items::{ErrorClass, FailedItem, FailedTarget};
+use crate::helper_methods::get_with_retry_and_headers;
+use crate::html_parsing::*;
+use crate::progress::Stat;
+use crate::scrape_context::ScrapeContext;
+use crate::scrape_state::ScrapeState;
+use crate::snapshot::{read_snapshot, write_snapshot};
+use crate::{REQUEST_RETRY_COUNT, RETRY_REQUEST_WAIT_DURATION};
+use chrono::{DateTime, Utc};
+use reqwest::header::HeaderMap;
+use reqwest::StatusCode;
+use scraper::{Html, Selector};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{Read, Write};
+use std::ops::RangeInclusive;
+use std::path::Path;
+use tracing::Instrument;
+
+/// Gets the page's HTML, saving a snapshot of it to the path.
+///
+/// When replaying, or when the archive says the page hasn't changed since its snapshot, the snapshot is read instead.
+///
+/// # Errors
+/// - The page couldn't be fetched, or there's no snapshot of it to read.
+async fn fetch_page(
+  context: &ScrapeContext,
+  page_url: &str,
+  snapshot_path: &Path,
+) -> ScrapeResult<String> {
+  if context.replay {
+    return read_snapshot(page_url, snapshot_path);
+  }
+
+  let Some((page_html, response_headers)) = download_page(context, page_url, snapshot_path).await?
+  else {
+    return read_snapshot(page_url, snapshot_path);
+  };
+
+  // Dry runs don't write anything.
+  if context.download_plan.is_some() {
+    return Ok(page_html);
+  }
+
+  match write_snapshot(snapshot_path, &page_html) {
+    Ok(snapshot_bytes) => {
+      context.disk_guard.record_written(snapshot_bytes);
+
+      // The cache only points at the snapshot, so it's only kept once there's one to point at.
+      if let Some(page_cache) = &context.page_cache {
+        if let Err(error) = page_cache.store(page_url, &response_headers) {
+          tracing::error!("Failed to cache the page. Reason: `{error:?}`");
+        }
+      }
+    }
+    Err(error) => tracing::error!("Failed to save a snapshot of the page. Reason: `{error:?}`"),
+  }
+
+  Ok(page_html)
+}
+
+/// Gets the page's HTML along with the response's headers, revalidating the page's snapshot if it's cached.
+///
+/// Returns `None` if the page hasn't changed since its snapshot was saved.
+///
+/// # Errors
+/// - The page couldn't be fetched.
+async fn download_page(
+  context: &ScrapeContext,
+  page_url: &str,
+  snapshot_path: &Path,
+) -> ScrapeResult<Option<(String, HeaderMap)>> {
+  let cached_page = context
+    .page_cache
+    .as_ref()
+    .filter(|_| snapshot_path.exists())
+    .and_then(|page_cache| page_cache.get(page_url));
+  let conditional_headers = cached_page
+    .as_ref()
+    .map(|cached_page| cached_page.conditional_headers())
+    .unwrap_or_default();
+
+  let response = get_with_retry_and_headers(
+    &context.client,
+    page_url.to_string(),
+    &conditional_headers,
+    REQUEST_RETRY_COUNT,
+    &context.rate_limiter,
+    RETRY_REQUEST_WAIT_DURATION,
+  )
+  .await?;
+
+  if response.status() == StatusCode::NOT_MODIFIED {
+    let Some(cached_page) = cached_page else {
+      return Err(ScrapeError::HttpStatus {
+        url: page_url.to_string(),
+        status: response.status(),
+      });
+    };
+
+    tracing::info!(
+      "Page is unchanged since {}, using its snapshot.",
+      cached_page.cached_at
+    );
+
+    return Ok(None);
+  }
+
+  let response_headers = response.headers().clone();
+  let response_body = response
+    .text()
+    .await
+    .map_err(|error| ScrapeError::network(page_url, error))?;
+
+  Ok(Some((response_body, response_headers)))
+}
+
+#[tracing::instrument(name = "page", skip(context))]
+pub async fn get_thread_page_id(
+  context: &ScrapeContext,
+  page_number: usize,
+) -> ScrapeResult<Vec<String>> {
+  tracing::info!("Reading page.");
+
+  let page_url = context.backend.search_page_url(page_number);
+  let snapshot_path = context
+    .storage
+    .search_page_snapshot_path(&context.backend.search_url, page_number);
+  let response_body = fetch_page(context, &page_url, &snapshot_path).await?;
+  let parsed_response = Html::parse_document(&response_body);
+
+  let article_selector = Selector::parse("article").unwrap();
+  let selection = parsed_response.select(&article_selector);
+
+  Ok(
+    selection
+      .into_iter()
+      .map(|element| element.value())
+      .map(|element_value| element_value.id())
+      .filter_map(|id| id.map(|id| id.to_string()))
+      .collect(),
+  )
+}
+
+#[tracing::instrument(name = "thread", skip(context))]
+pub async fn download_images_and_urls_of_interest_from_thread(
+  context: &ScrapeContext,
+  thread_id: &str,
+) -> ScrapeResult<()> {
+  tracing::info!("Requesting thread page.");
+  let thread_list_url = context.backend.thread_page_url(thread_id);
+  let snapshot_path = context
+    .storage
+    .thread_snapshot_path(&context.backend.board, thread_id);
+  let response_text = fetch_page(context, &thread_list_url, &snapshot_path).await?;
+
+  tracing::info!("Got response.");
+
+  let posts = extract_posts(context, &thread_list_url, &response_text)?;
+
+  for post in posts {
+    if context.shutdown.is_stopping() {
+      tracing::info!("Stopping before the rest of the thread is processed.");
+      break;
+    }
+
+    let post_context = PostContext {
+      board: &context.backend.board,
+      thread_id,
+      post_id: &post.post_id,
+      timestamp: post.timestamp,
+    };
+
+    context.events.send(ScrapeEvent::PostDiscovered {
+      thread_id: thread_id.to_string(),
+      post_id: post.post_id.clone(),
+      timestamp: post_context.timestamp,
+    });
+
+    process_post(context, &post_context, post.image_data, post.hyperlinks).await?;
+  }
+
+  Ok(())
+}
+
+/// What's needed from a post to process it, taken out of the page so the page isn't held across downloads.
+struct ExtractedPost {
+  post_id: String,
+  timestamp: Option<DateTime<Utc>>,
+  image_data: Option<MediaData>,
+  hyperlinks: Vec<String>,
+}
+
+/// Every post on the thread page that passes the post filter and has media or hyperlinks.
+///
+/// # Errors
+/// - [`ScrapeError::Markup`] The thread page has no posts.
+fn extract_posts(
+  context: &ScrapeContext,
+  thread_url: &str,
+  page_html: &str,
+) -> ScrapeResult<Vec<ExtractedPost>> {
+  let response_html = Html::parse_document(page_html);
+
+  let post_selector = Selector::parse("article").unwrap();
+  let mut posts = response_html.select(&post_selector).peekable();
+
+  // Every thread page has at least the opening post.
+  if posts.peek().is_none() {
+    return Err(ScrapeError::Markup {
+      url: thread_url.to_string(),
+      reason: "The thread page has no posts.".to_string(),
+    });
+  }
+
+  let mut extracted_posts = vec![];
+
+  for post in posts {
+    let post_value = post.value();
+
+    // The thread's own article only wraps its posts, so it isn't checked against the post filter.
+    if post_value.has_class("post_is_op", scraper::CaseSensitivity::CaseSensitive)
+      || post_value.has_class("thread", scraper::CaseSensitivity::CaseSensitive)
+      || post_value.has_class(
+        "backlink_container",
+        scraper::CaseSensitivity::CaseSensitive,
+      )
+    {
+      continue;
+    }
+
+    let Some(post_id) = post_value.id() else {
+      tracing::warn!("Attempted to read an article with a missing ID.\n{post_value:?}",);
+
+      continue;
+    };
+
+    if let Err(rejection) = context.post_filter.check(&post) {
+      tracing::debug!("Skipping post {post_id}, {rejection}.");
+      context.run_stats.record_filter_rejection(rejection);
+
+      continue;
+    }
+
+    let image_data = extract_media_url_from_post(&post);
+    let hyperlinks = extract_hyperlinks_from_post(&post, &context.banned_urls).unwrap_or_default();
+
+    if image_data.is_none() && hyperlinks.is_empty() {
+      continue;
+    }
+
+    extracted_posts.push(ExtractedPost {
+      post_id: post_id.to_string(),
+      timestamp: extract_post_timestamp(&post),
+      image_data,
+      hyperlinks,
+    });
+  }
+
+  Ok(extracted_posts)
+}
+
+#[tracing::instrument(name = "post", skip_all, fields(post_id = post.post_id))]
+async fn process_post(
+  context: &ScrapeContext,
+  post: &PostContext<'_>,
+  image_data: Option<MediaData>,
+  hyperlinks: Vec<String>,
+) -> ScrapeResult<()> {
+  if let Some(image_data) = image_data {
+    match context.media_filter.check(&image_data) {
+      Ok(()) => {
+        context.run_stats.increment(Stat::MediaQueued);
+        context.events.send(ScrapeEvent::MediaDiscovered {
+          thread_id: post.thread_id.to_string(),
+          post_id: post.post_id.to_string(),
+          media: image_data.clone(),
+        });
+
+        // Replays only re-extract, nothing is downloaded.
+        let download_result = if context.replay {
+          context.run_stats.increment(Stat::MediaSkipped);
+
+          Ok(())
+        } else {
+          image_data.clone().download(context, post, "").await
+        };
+
+        match download_result {
+          Ok(()) => (),
+          Err(ScrapeError::HostUnavailable { host }) => {
+            tracing::warn!("{host:?} is down, deferring media to the end of the run.");
+            context
+              .deferred_media
+              .push(DeferredMedia::new(post, image_data, ""));
+          }
+          Err(error @ ScrapeError::Disallowed { .. }) => {
+            tracing::warn!("Skipping media. {error}");
+            context.run_stats.increment(Stat::MediaFailed);
+            context.failed_items.record(
+              FailedTarget::Media(Box::new(DeferredMedia::new(post, image_data, ""))),
+              &error,
+            );
+          }
+          // The run is stopping, and the thread is picked up again by the next one.
+          Err(error @ ScrapeError::DiskLimit(_)) => return Err(error),
+          Err(error) => {
+            context.run_stats.increment(Stat::MediaFailed);
+
+            return Err(error);
+          }
+        }
+      }
+      Err(rejection) => {
+        tracing::info!("Skipping media, {rejection}.");
+        context.run_stats.record_filter_rejection(rejection);
+      }
+    }
+  }
+
+  if !hyperlinks.is_empty() {
+    tracing::info!("Extracted hyperlinks of interest: {hyperlinks:?}");
+    context.events.send(ScrapeEvent::HyperlinksDiscovered {
+      thread_id: post.thread_id.to_string(),
+      post_id: post.post_id.to_string(),
+      hyperlinks: hyperlinks.clone(),
+    });
+    write_hyperlinks_to_disk(context, hyperlinks, post).await?;
+  }
+
+  Ok(())
+}
+
+async fn write_hyperlinks_to_disk(
+  context: &ScrapeContext,
+  hyperlinks: Vec<String>,
+  post: &PostContext<'_>,
+) -> ScrapeResult<()> {
+  let PostContext {
+    thread_id, post_id, ..
+  } = post;
+  let file_path = context.storage.hyperlink_path(&post.template_values())?;
+
+  if let Some(download_plan) = &context.download_plan {
+    download_plan.add(PlannedItem::Hyperlinks {
+      thread_id: thread_id.to_string(),
+      post_id: post_id.to_string(),
+      hyperlinks,
+      target_path: file_path,
+    });
+
+    return Ok(());
+  }
+
+  if let Some(hyperlink_parent_dirs) = file_path.parent() {
+    if !hyperlink_parent_dirs.exists() {
+      tracing::info!("Creating dir for hyperlinks");
+      fs::create_dir_all(hyperlink_parent_dirs)?;
+    }
+  }
+
+  let mut hyperlink_file = fs::OpenOptions::new()
+    .append(true)
+    .create(true)
+    .truncate(false)
+    .open(&file_path)?;
+
+  for hyperlink in hyperlinks {
+    let line = format!("{}-{}: {}", thread_id, post_id, hyperlink);
+
+    if let Err(error) = writeln!(hyperlink_file, "{}", line) {
+      tracing::error!("Failed to write a hyperlink to file. {{{hyperlink}}}. Reason: `{error:?}`",);
+    }
+  }
+
+  Ok(())
+}
+
+/// Downloads from every thread found on the search pages in the range, resuming from the saved state if there is one.
+///
+/// Returns where the run got to if it was stopped early, either by shutdown or by the archive going down.
+pub async fn download_images_from_page_range(
+  context: &ScrapeContext,
+  page_range: RangeInclusive<usize>,
+) -> Option<ScrapeState> {
+  let run_stats = &context.run_stats;
+  let state_file_path = context.storage.scrape_state_path();
+  // Dry runs and replays leave the saved state for real runs.
+  let scrape_state = if context.download_plan.is_some() || context.replay {
+    Ok(ScrapeState::in_memory(*page_range.start()))
+  } else {
+    ScrapeState::load(&state_file_path, *page_range.start())
+  };
+  let mut scrape_state = match scrape_state {
+    Ok(scrape_state) => scrape_state,
+    Err(error) => {
+      tracing::error!(
+        "Failed to load the scrape state, starting from the beginning. Reason: `{error:?}`"
+      );
+      ScrapeState::new(&state_file_path, *page_range.start())
+    }
+  };
+  let page_range = scrape_state.next_page..=*page_range.end();
+  run_stats.add(Stat::PagesTotal, page_range.clone().count() as u64);
+  let mut archive_down = false;
+
+  for page_number in page_range {
+    if context.shutdown.is_stopping() {
+      break;
+    }
+
+    let thread_id_result = get_thread_page_id(context, page_number).await;
+    let thread_ids = match thread_id_result {
+      Ok(thread_ids) => thread_ids,
+      Err(ScrapeError::HostUnavailable { host }) => {
+        tracing::error!(
+          "The archive at {host:?} is down, stopping so a later run can pick up from here."
+        );
+        archive_down = true;
+
+        break;
+      }
+      Err(error) => {
+        tracing::error!("Failed to read page number {page_number:?}. Reason: `{error:?}`");
+        run_stats.increment(Stat::PageFailed);
+        context
+          .failed_items
+          .record(FailedTarget::Page { page_number }, &error);
+        scrape_state.complete_page(page_number);
+
+        continue;
+      }
+    };
+    let thread_ids: Vec<String> = thread_ids
+      .into_iter()
+      .filter(|thread_id| !scrape_state.completed_threads.contains(thread_id))
+      .collect();
+
+    tracing::info!("Got thread ids. Processing {:?}", thread_ids);
+    context.events.send(ScrapeEvent::ThreadsDiscovered {
+      page_number,
+      thread_ids: thread_ids.clone(),
+    });
+    run_stats.add(Stat::ThreadQueued, thread_ids.len() as u64);
+
+    for thread_id in thread_ids {
+      if context.shutdown.is_stopping() {
+        break;
+      }
+
+      let result = download_images_and_urls_of_interest_from_thread(context, &thread_id).await;
+
+      // A thread cut short by shutdown is picked up again by the next run.
+      if context.shutdown.is_stopping() {
+        break;
+      }
+
+      match result {
+        Ok(()) => run_stats.increment(Stat::ThreadDone),
+        Err(ScrapeError::HostUnavailable { host }) => {
+          tracing::error!(
+            "The archive at {host:?} is down, stopping so a later run can pick up from here."
+          );
+          archive_down = true;
+
+          break;
+        }
+        Err(error) => {
+          tracing::error!(
+            "An error occurred when attempting to download from thread {thread_id:?}. Reason: `{error:?}`",
+          );
+          run_stats.increment(Stat::ThreadFailed);
+          context.failed_items.record(
+            FailedTarget::Thread {
+              thread_id: thread_id.clone(),
+            },
+            &error,
+          );
+        }
+      }
+
+      scrape_state.complete_thread(&thread_id);
+    }
+
+    if context.shutdown.is_stopping() || archive_down {
+      break;
+    }
+
+    run_stats.increment(Stat::PageDone);
+    scrape_state.complete_page(page_number);
+  }
+
+  if context.shutdown.is_stopping() || archive_down {
+    tracing::info!(
+      "Stopped early. A resumed run will pick up at {}.",
+      scrape_state.resume_point()
+    );
+
+    return Some(scrape_state);
+  }
+
+  scrape_state.finish();
+
+  None
+}
+
+/// Downloads from each of the threads in order, bypassing search entirely.
+///
+/// Stops early on shutdown, or if the archive goes down.
+/// When the archive goes down, the threads left are recorded as failed so they can be retried.
+///
+/// Returns the threads a shutdown stopped it from finishing, starting with the one in flight.
+pub async fn download_images_from_thread_ids<'a>(
+  context: &ScrapeContext,
+  thread_ids: &'a [String],
+) -> &'a [String] {
+  let run_stats = &context.run_stats;
+  run_stats.add(Stat::ThreadQueued, thread_ids.len() as u64);
+
+  for (position, thread_id) in thread_ids.iter().enumerate() {
+    if context.shutdown.is_stopping() {
+      log_unprocessed_threads(&thread_ids[position..]);
+
+      return &thread_ids[position..];
+    }
+
+    let result = download_images_and_urls_of_interest_from_thread(context, thread_id).await;
+
+    // A thread cut short by shutdown counts as unprocessed along with the ones after it.
+    if context.shutdown.is_stopping() {
+      log_unprocessed_threads(&thread_ids[position..]);
+
+      return &thread_ids[position..];
+    }
+
+    match result {
+      Ok(()) => run_stats.increment(Stat::ThreadDone),
+      Err(error @ ScrapeError::HostUnavailable { .. }) => {
+        tracing::error!("Stopping before the rest of the threads. {error}");
+        run_stats.add(Stat::ThreadFailed, thread_ids[position..].len() as u64);
+
+        for thread_id in &thread_ids[position..] {
+          context.failed_items.record(
+            FailedTarget::Thread {
+              thread_id: thread_id.clone(),
+            },
+            &error,
+          );
+        }
+
+        // The rest were recorded as failed, so a retry picks them up rather than the caller.
+        return &[];
+      }
+      Err(error) => {
+        tracing::error!(
+          "An error occurred when attempting to download from thread {thread_id:?}. Reason: `{error:?}`",
+        );
+        run_stats.increment(Stat::ThreadFailed);
+        context.failed_items.record(
+          FailedTarget::Thread {
+            thread_id: thread_id.clone(),
+          },
+          &error,
+        );
+      }
+    }
+  }
+
+  &[]
+}
+
+fn log_unprocessed_threads(unprocessed_thread_ids: &[String]) {
+  if let Some(first_thread_id) = unprocessed_thread_ids.first() {
+    tracing::warn!(
+      "Stopping before thread {first_thread_id:?}, leaving {} threads unprocessed.",
+      unprocessed_thread_ids.len()
+    );
+  }
+}
+
+/// The file is expected to be in the format of `thread_id-post_id: url.extension`.
+/// example:
+/// ```text
+/// 58931442-58935074: https://files.catbox.moe/1qk6mu.mp3
+/// 46910262-46936051: https://files.catbox.moe/28fgj6.png
+/// 63361527-63109637: https://files.catbox.moe/29bg8r.png
+/// 48846709-48887663: https://files.catbox.moe/2hogqc.png
+/// 73339334-73391757: https://files.catbox.moe/2ifgg7.mp4
+/// 55924632-55962850: https://files.catbox.moe/2lhgt8.mp3
+/// 80352791-80373537: https://files.catbox.moe/2n9g6f.png
+/// 48472611-48561502: https://files.catbox.moe/2otgte.mp4
+/// 48700691-48742488: https://files.catbox.moe/2regli.mp4
+/// ```
+///
+/// # Errors
+/// - The file couldn't be read.
+pub async fn download_images_from_file_list<P: AsRef<Path>>(
+  context: &ScrapeContext,
+  file_path: P,
+) -> ScrapeResult<()> {
+  let file_path = file_path.as_ref();
+  let mut file_list = fs::File::open(file_path)?;
+  let mut urls = String::new();
+  file_list.read_to_string(&mut urls)?;
+
+  let mut checked_post_ids: HashMap<String, usize> = HashMap::new();
+  let url_data: Vec<(String, String, MediaData)> = urls
+    .lines()
+    .filter_map(|line| {
+      let line_data: Vec<&str> = line.splitn(2, ": ").collect();
+      let thread_data: Vec<&str> = line_data.first()?.splitn(2, "-").collect();
+
+      let url = line_data.get(1)?.to_string();
+      let extension = url.split('.').next_back()?.to_string();
+      let image_data = MediaData {
+        url,
+        extension,
+        original_name: None,
+        md5: None,
+        advertised_size: None,
+        dimensions: None,
+      };
+
+      Some((
+        thread_data.first()?.to_string(),
+        thread_data.get(1)?.to_string(),
+        image_data,
+      ))
+    })
+    .collect();
+
+  // TEMP
+  let post_ids: Vec<String> = url_data
+    .clone()
+    .into_iter()
+    .map(|(_, post_id, _)| post_id)
+    .collect();
+  let mut posts_with_frequency: HashMap<String, usize> = HashMap::new();
+
+  post_ids.iter().for_each(|post_id| {
+    let counter_entry = posts_with_frequency.entry(post_id.clone()).or_insert(0);
+    *counter_entry += 1;
+  });
+
+  let duplicate_posts: HashSet<String> = posts_with_frequency
+    .into_iter()
+    .filter_map(|(post_id, frequency)| (frequency > 1).then_some(post_id))
+    .collect();
+  // TEMP
+
+  for (thread_id, post_id, image_data) in url_data {
+    if context.shutdown.is_stopping() {
+      tracing::info!("Stopping before the rest of the file list is processed.");
+      break;
+    }
+
+    if !duplicate_posts.contains(&post_id) {
+      continue;
+    }
+
+    let file_appender = checked_post_ids
+      .get(&post_id)
+      .map(|count| (*count).to_string())
+      .unwrap_or_default();
+    let counter_entry = checked_post_ids.entry(post_id.clone()).or_insert(1);
+    *counter_entry += 1;
+
+    let post_span = tracing::info_span!("post", thread_id, post_id);
+
+    async {
+      let post_context = PostContext {
+        board: &context.backend.board,
+        thread_id: &thread_id,
+        post_id: &post_id,
+        timestamp: None,
+      };
+
+      match download_listed_media(context, &post_context, image_data.clone(), &file_appender).await
+      {
+        Ok(()) => (),
+        Err(ScrapeError::HostUnavailable { host }) => {
+          tracing::warn!("{host:?} is down, deferring media to the end of the run.");
+          context.deferred_media.push(DeferredMedia::new(
+            &post_context,
+            image_data,
+            &file_appender,
+          ));
+        }
+        // The run is stopping.
+        Err(ScrapeError::DiskLimit(_)) => (),
+        Err(ScrapeError::FilterRejected(rejection)) => {
+          tracing::info!("Skipping media, {rejection}.");
+          context.run_stats.record_filter_rejection(rejection);
+        }
+        Err(error) => {
+          context.run_stats.increment(Stat::MediaFailed);
+          tracing::error!("Image could not be downloaded. Reason: {error:?}");
+          context.failed_items.record(
+            FailedTarget::Media(Box::new(DeferredMedia::new(
+              &post_context,
+              image_data,
+              &file_appender,
+            ))),
+            &error,
+          );
+        }
+      }
+    }
+    .instrument(post_span)
+    .await;
+  }
+
+  Ok(())
+}
+
+async fn download_listed_media(
+  context: &ScrapeContext,
+  post: &PostContext<'_>,
+  image_data: MediaData,
+  file_appender: &str,
+) -> ScrapeResult<()> {
+  context.media_filter.check(&image_data)?;
+  context.run_stats.increment(Stat::MediaQueued);
+
+  image_data.download(context, post, file_appender).await
+}
+
+/// Retries media deferred while its host was down, waiting for each host to be due a probe first.
+///
+/// Media whose host is still down is put back in the queue for a later run.
+pub async fn download_deferred_media(context: &ScrapeContext) {
+  let deferred_media = context.deferred_media.take();
+
+  if !deferred_media.is_empty() {
+    tracing::info!(
+      "Retrying {} media deferred while their hosts were down.",
+      deferred_media.len()
+    );
+  }
+
+  let mut hosts_still_down: HashSet<String> = HashSet::new();
+
+  for deferred in deferred_media {
+    let host = host_of(&deferred.media.url).unwrap_or_default();
+
+    if context.shutdown.is_stopping() || hosts_still_down.contains(&host) {
+      context.deferred_media.push(deferred);
+
+      continue;
+    }
+
+    context
+      .client
+      .circuit_breaker()
+      .wait_for_probe(&deferred.media.url)
+      .await;
+
+    let post_context = deferred.post_context();
+    let result = deferred
+      .media
+      .clone()
+      .download(context, &post_context, &deferred.file_appender)
+      .await;
+
+    match result {
+      Ok(()) => (),
+      Err(ScrapeError::HostUnavailable { host }) => {
+        hosts_still_down.insert(host);
+        context.deferred_media.push(deferred);
+      }
+      Err(ScrapeError::DiskLimit(_)) => context.deferred_media.push(deferred),
+      Err(error) => {
+        context.run_stats.increment(Stat::MediaFailed);
+        tracing::error!("Deferred media could not be downloaded. Reason: {error:?}");
+        context
+          .failed_items
+          .record(FailedTarget::Media(Box::new(deferred)), &error);
+      }
+    }
+  }
+
+  if !context.deferred_media.is_empty() {
+    tracing::warn!(
+      "{} media are left for a later run, as their hosts are still down.",
+      context.deferred_media.len()
+    );
+  }
+
+  context
+    .run_stats
+    .add(Stat::MediaDeferred, context.deferred_media.len() as u64);
+}
+
+/// Reprocesses the items that failed with any of the classes, or every failed item if none are given.
+///
+/// Items that fail again are recorded with their new reason, and items not reached before shutdown are kept as they were.
+/// A page cut short by shutdown is kept as the threads it didn't get through.
+pub async fn retry_failed_items(context: &ScrapeContext, error_classes: &[ErrorClass]) {
+  let failed_items = context.failed_items.take_matching(error_classes);
+  tracing::info!("Retrying {} failed items.", failed_items.len());
+
+  for failed_item in failed_items {
+    if context.shutdown.is_stopping() {
+      context.failed_items.push(failed_item);
+
+      continue;
+    }
+
+    retry_failed_item(context, failed_item).await;
+  }
+}
+
+async fn retry_failed_item(context: &ScrapeContext, failed_item: FailedItem) {
+  let FailedItem {
+    target,
+    error_class,
+    reason,
+    failed_at,
+  } = failed_item;
+  tracing::info!("Retrying {target:?}, which failed with `{reason}`");
+
+  match target {
+    FailedTarget::Page { page_number } => match get_thread_page_id(context, page_number).await {
+      Ok(thread_ids) => {
+        let unprocessed_thread_ids = download_images_from_thread_ids(context, &thread_ids).await;
+
+        if unprocessed_thread_ids.is_empty() {
+          context.run_stats.increment(Stat::PageDone);
+        }
+
+        // Threads a shutdown stopped before keep the page's failure, so the next retry picks them up.
+        for thread_id in unprocessed_thread_ids {
+          context.failed_items.push(FailedItem {
+            target: FailedTarget::Thread {
+              thread_id: thread_id.clone(),
+            },
+            error_class,
+            reason: reason.clone(),
+            failed_at,
+          });
+        }
+      }
+      Err(error) => {
+        tracing::error!("Failed to read page number {page_number:?}. Reason: `{error:?}`");
+        context.run_stats.increment(Stat::PageFailed);
+        context
+          .failed_items
+          .record(FailedTarget::Page { page_number }, &error);
+      }
+    },
+    FailedTarget::Thread { thread_id } => {
+      let stopped_early =
+        !download_images_from_thread_ids(context, std::slice::from_ref(&thread_id))
+          .await
+          .is_empty();
+
+      // The thread was cut short, so it's kept for the next retry as it was.
+      if stopped_early {
+        context.failed_items.push(FailedItem {
+          target: FailedTarget::Thread { thread_id },
+          error_class,
+          reason,
+          failed_at,
+        });
+      }
+    }
+    FailedTarget::Media(failed_media) => {
+      context.run_stats.increment(Stat::MediaQueued);
+
+      let post_context = failed_media.post_context();
+      let result = failed_media
+        .media
+        .clone()
+        .download(context, &post_context, &failed_media.file_appender)
+        .await;
+
+      match result {
+        Ok(()) => (),
+        Err(ScrapeError::HostUnavailable { host }) => {
+          tracing::warn!("{host:?} is down, deferring media to the end of the run.");
+          context.deferred_media.push(*failed_media);
+        }
+        // The run is stopping, so the media is kept for the next retry as it was.
+        Err(error @ (ScrapeError::Aborted { .. } | ScrapeError::DiskLimit(_))) => {
+          tracing::info!("Stopping before the media could be retried. {error}");
+          context.failed_items.push(FailedItem {
+            target: FailedTarget::Media(failed_media),
+            error_class,
+            reason,
+            failed_at,
+          });
+        }
+        Err(error) => {
+          context.run_stats.increment(Stat::MediaFailed);
+          tracing::error!("Media could not be downloaded. Reason: {error:?}");
+          context
+            .failed_items
+            .record(FailedTarget::Media(failed_media), &error);
+        }
+      }
+    }
+  }
+}
@@ -0,0 +1,60 @@
+use crate::backend::ArchiveBackend;
+use crate::deferred_media::DeferredQueue;
+use crate::disk_guard::DiskGuard;
+use crate::download_plan::DownloadPlan;
+use crate::error::{ScrapeError, ScrapeResult};
+use crate::events::EventSender;
+use crate::failed_items::FailedQueue;
+use crate::http_client::HttpClient;
+use crate::media_filter::MediaFilter;
+use crate::media_index::MediaIndex;
+use crate::page_cache::PageCache;
+use crate::post_filter::PostFilter;
+use crate::progress::RunStats;
+use crate::ratelimiter::DeviationRateLimiter;
+use crate::shutdown::ShutdownSignal;
+use crate::storage::Storage;
+
+/// Everything shared between the stages of a scrape.
+#[derive(Clone)]
+pub struct ScrapeContext {
+  pub client: HttpClient,
+  pub rate_limiter: DeviationRateLimiter,
+  pub backend: ArchiveBackend,
+  pub storage: Storage,
+  pub disk_guard: DiskGuard,
+  pub media_index: MediaIndex,
+  /// Not set if pages are always downloaded in full.
+  pub page_cache: Option<PageCache>,
+  /// Media put off while its host was down.
+  pub deferred_media: DeferredQueue,
+  /// Everything that failed, kept for `retry-failed`.
+  pub failed_items: FailedQueue,
+  pub media_filter: MediaFilter,
+  pub post_filter: PostFilter,
+  /// Hyperlinks containing any of these are ignored.
+  pub banned_urls: Vec<String>,
+  pub run_stats: RunStats,
+  pub shutdown: ShutdownSignal,
+  pub events: EventSender,
+  /// Set when pages are read from their saved snapshots, where nothing is sent to the archive and no media is downloaded.
+  pub replay: bool,
+  /// Set for dry runs, where nothing is downloaded or written and planned work is collected here instead.
+  pub download_plan: Option<DownloadPlan>,
+}
+
+impl ScrapeContext {
+  /// Checks that `upcoming_bytes` more can be written, stopping the run if they can't.
+  ///
+  /// # Errors
+  /// - [`ScrapeError::DiskLimit`] Writing them would go over a disk limit.
+  pub fn check_disk_space(&self, upcoming_bytes: u64) -> ScrapeResult<()> {
+    let result = self.disk_guard.check(upcoming_bytes);
+
+    if let Err(ScrapeError::DiskLimit(reason)) = &result {
+      self.shutdown.request_stop(reason);
+    }
+
+    result
+  }
+}
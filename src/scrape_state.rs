@@ -0,0 +1,114 @@
+use crate::error::ScrapeResult;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Where a page range scrape got to, so a stopped run can pick up from there.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScrapeState {
+  /// The first page that hasn't been fully processed.
+  pub next_page: usize,
+  /// The threads already processed on `next_page`.
+  pub completed_threads: BTreeSet<String>,
+  /// Where the state is saved, not set for states that are only kept in memory.
+  #[serde(skip)]
+  state_file_path: Option<PathBuf>,
+}
+
+impl ScrapeState {
+  /// A fresh state starting from `first_page`.
+  pub fn new<P: AsRef<Path>>(state_file_path: P, first_page: usize) -> Self {
+    Self {
+      next_page: first_page,
+      completed_threads: BTreeSet::new(),
+      state_file_path: Some(state_file_path.as_ref().to_path_buf()),
+    }
+  }
+
+  /// A state that's never saved, for runs that shouldn't write anything.
+  pub fn in_memory(first_page: usize) -> Self {
+    Self {
+      next_page: first_page,
+      completed_threads: BTreeSet::new(),
+      state_file_path: None,
+    }
+  }
+
+  /// Loads the saved state, starting from `first_page` if there is none.
+  ///
+  /// # Errors
+  /// - The state file exists but couldn't be read or parsed.
+  pub fn load<P: AsRef<Path>>(state_file_path: P, first_page: usize) -> ScrapeResult<Self> {
+    let state_file_path = state_file_path.as_ref().to_path_buf();
+
+    if !state_file_path.exists() {
+      return Ok(Self::new(state_file_path, first_page));
+    }
+
+    let mut state: Self = serde_json::from_str(&fs::read_to_string(&state_file_path)?)?;
+    tracing::info!(
+      "Resuming from page {} with {} threads already done.",
+      state.next_page,
+      state.completed_threads.len()
+    );
+
+    state.next_page = state.next_page.max(first_page);
+    state.state_file_path = Some(state_file_path);
+
+    Ok(state)
+  }
+
+  pub fn complete_thread(&mut self, thread_id: &str) {
+    self.completed_threads.insert(thread_id.to_string());
+    self.save();
+  }
+
+  pub fn complete_page(&mut self, page_number: usize) {
+    self.next_page = page_number + 1;
+    self.completed_threads.clear();
+    self.save();
+  }
+
+  /// Removes the saved state once the whole range has been processed.
+  pub fn finish(&self) {
+    let Some(state_file_path) = &self.state_file_path else {
+      return;
+    };
+
+    if let Err(error) = fs::remove_file(state_file_path) {
+      tracing::error!("Failed to remove the scrape state file. Reason: `{error:?}`");
+    }
+  }
+
+  /// A description of where a resumed run will pick up from.
+  pub fn resume_point(&self) -> String {
+    format!(
+      "page {} ({} threads of it already done)",
+      self.next_page,
+      self.completed_threads.len()
+    )
+  }
+
+  fn save(&self) {
+    if let Err(error) = self.try_save() {
+      tracing::error!("Failed to save the scrape state. Reason: `{error:?}`");
+    }
+  }
+
+  fn try_save(&self) -> ScrapeResult<()> {
+    let Some(state_file_path) = &self.state_file_path else {
+      return Ok(());
+    };
+
+    if let Some(state_parent_dirs) = state_file_path.parent() {
+      if !state_parent_dirs.exists() {
+        fs::create_dir_all(state_parent_dirs)?;
+      }
+    }
+
+    fs::write(state_file_path, serde_json::to_string_pretty(self)?)?;
+
+    Ok(())
+  }
+}
@@ -0,0 +1,107 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+
+/// Tracks SIGINT/SIGTERM so the scrape can stop cleanly.
+///
+/// The first signal asks the run to stop scheduling new work and let in-flight downloads finish.
+/// A second signal aborts in-flight downloads too.
+///
+/// The run can also stop itself through [`ShutdownSignal::request_stop`], e.g. when the disk is filling up.
+#[derive(Debug, Clone, Default)]
+pub struct ShutdownSignal {
+  signal_count: Arc<AtomicUsize>,
+  notify: Arc<Notify>,
+  stop_reason: Arc<Mutex<Option<String>>>,
+}
+
+impl ShutdownSignal {
+  /// Starts listening for shutdown signals in the background.
+  pub fn listen() -> Self {
+    let shutdown_signal = Self::default();
+
+    tokio::spawn({
+      let shutdown_signal = shutdown_signal.clone();
+
+      async move {
+        loop {
+          wait_for_signal().await;
+
+          let signal_count = shutdown_signal.signal_count.fetch_add(1, Ordering::SeqCst) + 1;
+          shutdown_signal.notify.notify_waiters();
+
+          if signal_count == 1 {
+            tracing::warn!(
+              "Received a shutdown signal, finishing in-flight work. Signal again to abort it."
+            );
+            eprintln!(
+              "Shutting down after in-flight downloads finish. Press Ctrl-C again to abort them."
+            );
+          } else {
+            tracing::warn!("Received a second shutdown signal, aborting in-flight work.");
+            eprintln!("Aborting in-flight downloads.");
+          }
+        }
+      }
+    });
+
+    shutdown_signal
+  }
+
+  /// Stops the run the same way a first signal would, keeping the reason for it.
+  pub fn request_stop(&self, reason: impl Into<String>) {
+    let reason = reason.into();
+    tracing::error!("Stopping the run. {reason}");
+
+    self.stop_reason.lock().unwrap().get_or_insert(reason);
+    // Never escalates to aborting in-flight work.
+    let _ = self
+      .signal_count
+      .compare_exchange(0, 1, Ordering::SeqCst, Ordering::SeqCst);
+    self.notify.notify_waiters();
+  }
+
+  /// Why the run stopped itself, if it did.
+  pub fn stop_reason(&self) -> Option<String> {
+    self.stop_reason.lock().unwrap().clone()
+  }
+
+  /// Whether new work should no longer be started.
+  pub fn is_stopping(&self) -> bool {
+    self.signal_count.load(Ordering::SeqCst) >= 1
+  }
+
+  pub fn is_aborting(&self) -> bool {
+    self.signal_count.load(Ordering::SeqCst) >= 2
+  }
+
+  /// Resolves once in-flight work should be abandoned.
+  pub async fn aborted(&self) {
+    loop {
+      let notified = self.notify.notified();
+
+      if self.is_aborting() {
+        return;
+      }
+
+      notified.await;
+    }
+  }
+}
+
+#[cfg(unix)]
+async fn wait_for_signal() {
+  use tokio::signal::unix::{signal, SignalKind};
+
+  let mut terminate = signal(SignalKind::terminate()).unwrap();
+
+  tokio::select! {
+    _ = tokio::signal::ctrl_c() => {}
+    _ = terminate.recv() => {}
+  }
+}
+
+#[cfg(not(unix))]
+async fn wait_for_signal() {
+  let _ = tokio::signal::ctrl_c().await;
+}
@@ -0,0 +1,84 @@
+use crate::error::{ScrapeError, ScrapeResult};
+use crate::storage::Storage;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Favours a smaller snapshot over compression speed, as pages are small and only written once per fetch.
+const SNAPSHOT_COMPRESSION_LEVEL: i32 = 9;
+
+/// Compresses the page's HTML to the path, replacing any older snapshot of it.
+///
+/// # Errors
+/// - The snapshot couldn't be compressed or written.
+pub fn write_snapshot(snapshot_path: &Path, html: &str) -> ScrapeResult<u64> {
+  if let Some(parent_dir) = snapshot_path.parent() {
+    if !parent_dir.exists() {
+      fs::create_dir_all(parent_dir)?;
+    }
+  }
+
+  let compressed_html = zstd::encode_all(html.as_bytes(), SNAPSHOT_COMPRESSION_LEVEL)?;
+  fs::write(snapshot_path, &compressed_html)?;
+
+  Ok(compressed_html.len() as u64)
+}
+
+/// # Errors
+/// - [`ScrapeError::MissingSnapshot`] No snapshot of the page was saved.
+/// - The snapshot couldn't be read or decompressed.
+pub fn read_snapshot(page_url: &str, snapshot_path: &Path) -> ScrapeResult<String> {
+  let compressed_html = match fs::read(snapshot_path) {
+    Ok(compressed_html) => compressed_html,
+    Err(error) if error.kind() == io::ErrorKind::NotFound => {
+      return Err(ScrapeError::MissingSnapshot {
+        url: page_url.to_string(),
+        path: snapshot_path.to_path_buf(),
+      })
+    }
+    Err(error) => return Err(error.into()),
+  };
+  let html = zstd::decode_all(compressed_html.as_slice())?;
+
+  String::from_utf8(html).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error).into())
+}
+
+/// The ID of every thread on the board with a saved snapshot, oldest first.
+///
+/// # Errors
+/// - The data directory couldn't be read.
+pub fn saved_thread_ids(storage: &Storage, board: &str) -> ScrapeResult<Vec<String>> {
+  let mut thread_ids = vec![];
+  let mut dirs = vec![storage.data_dir.clone()];
+
+  while let Some(dir) = dirs.pop() {
+    if !dir.exists() {
+      continue;
+    }
+
+    for entry in fs::read_dir(&dir)? {
+      let entry = entry?;
+
+      if entry.file_type()?.is_dir() {
+        dirs.push(entry.path());
+
+        continue;
+      }
+
+      let file_name = entry.file_name();
+      let Some(thread_id) = file_name.to_str().and_then(Storage::snapshot_thread_id) else {
+        continue;
+      };
+
+      // Snapshots of other boards, or left over from another layout, aren't where this board's would be.
+      if storage.thread_snapshot_path(board, thread_id) == entry.path() {
+        thread_ids.push(thread_id.to_string());
+      }
+    }
+  }
+
+  // Thread IDs count up, so sorting them numerically puts them in the order they were made.
+  thread_ids.sort_by_key(|thread_id| (thread_id.parse::<u64>().ok(), thread_id.clone()));
+
+  Ok(thread_ids)
+}
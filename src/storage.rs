@@ -0,0 +1,197 @@
+use crate::error::{ScrapeError, ScrapeResult};
+use crate::path_template::{sanitize_component, PathTemplate, TemplateValues};
+use md5::{Digest, Md5};
+use serde::Deserialize;
+use std::path::PathBuf;
+
+pub const DEFAULT_DATA_DESTINATION_DIR: &str = "data";
+/// Where media is saved under the data directory. See [`PathTemplate`] for the available fields.
+///
+/// Use `{original_name}` to keep the name the file was uploaded with, e.g. `{thread}/{post}_{original_name}.{ext}`.
+pub const DEFAULT_MEDIA_PATH_FORMAT: &str = "{thread}/{thread}-{post}-{appender}.{ext}";
+/// Where extracted hyperlinks are appended to under the data directory.
+pub const DEFAULT_HYPERLINK_PATH_FORMAT: &str = "urls.txt";
+/// Where a JSON line describing each saved media file is appended to under the data directory.
+pub const DEFAULT_METADATA_PATH_FORMAT: &str = "{thread}/metadata.jsonl";
+/// The file under the data directory tracking the MD5 of every downloaded media file.
+const MEDIA_INDEX_FILE_NAME: &str = "media_index.jsonl";
+/// The file under the data directory tracking how far a page range scrape got.
+const SCRAPE_STATE_FILE_NAME: &str = "scrape_state.json";
+/// The file under the data directory keeping media whose host was still down when a run ended.
+const DEFERRED_MEDIA_FILE_NAME: &str = "deferred_media.jsonl";
+/// The file under the data directory keeping every page, thread and media download that failed, until it's retried.
+const FAILED_ITEMS_FILE_NAME: &str = "failed_items.jsonl";
+/// The directory under the data directory that search and thread pages are cached in.
+const PAGE_CACHE_DIR_NAME: &str = "page_cache";
+/// Compressed copies of thread pages are named `thread_{thread}.html.zst`.
+const THREAD_SNAPSHOT_FILE_PREFIX: &str = "thread_";
+const SNAPSHOT_FILE_EXTENSION: &str = ".html.zst";
+/// The directory under the data directory that compressed copies of search pages are kept in, one directory per search.
+const SEARCH_PAGE_SNAPSHOT_DIR_NAME: &str = "search_pages";
+/// The directory under the data directory that media failing verification is moved to.
+const QUARANTINE_DIR_NAME: &str = "quarantine";
+
+/// Where and how everything a scrape produces is laid out on disk, set from the `[storage]` section of a profile.
+///
+/// example:
+/// ```toml
+/// [storage]
+/// data_dir = "archive"
+/// media_path_template = "{board}/{thread}/{post}_{original_name}.{ext}"
+/// hard_link_duplicate_media = false
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "StorageSettings")]
+pub struct Storage {
+  pub data_dir: PathBuf,
+  pub media_path_template: PathTemplate,
+  pub hyperlink_path_template: PathTemplate,
+  pub metadata_path_template: PathTemplate,
+  /// Whether media already downloaded for another post gets hard linked to its new path,
+  /// rather than only being referenced in the metadata.
+  pub hard_link_duplicate_media: bool,
+}
+
+/// [`Storage`] as written in a profile, before its templates are parsed.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct StorageSettings {
+  data_dir: PathBuf,
+  media_path_template: String,
+  hyperlink_path_template: String,
+  metadata_path_template: String,
+  hard_link_duplicate_media: bool,
+}
+
+impl Storage {
+  /// # Errors
+  /// - Any of the templates fail to render.
+  pub fn media_path(&self, values: &TemplateValues) -> ScrapeResult<PathBuf> {
+    Ok(self.data_dir.join(self.media_path_template.render(values)?))
+  }
+
+  /// # Errors
+  /// - Any of the templates fail to render.
+  pub fn hyperlink_path(&self, values: &TemplateValues) -> ScrapeResult<PathBuf> {
+    Ok(
+      self
+        .data_dir
+        .join(self.hyperlink_path_template.render(values)?),
+    )
+  }
+
+  /// # Errors
+  /// - Any of the templates fail to render.
+  pub fn metadata_path(&self, values: &TemplateValues) -> ScrapeResult<PathBuf> {
+    Ok(
+      self
+        .data_dir
+        .join(self.metadata_path_template.render(values)?),
+    )
+  }
+
+  pub fn media_index_path(&self) -> PathBuf {
+    self.data_dir.join(MEDIA_INDEX_FILE_NAME)
+  }
+
+  pub fn scrape_state_path(&self) -> PathBuf {
+    self.data_dir.join(SCRAPE_STATE_FILE_NAME)
+  }
+
+  pub fn deferred_media_path(&self) -> PathBuf {
+    self.data_dir.join(DEFERRED_MEDIA_FILE_NAME)
+  }
+
+  pub fn failed_items_path(&self) -> PathBuf {
+    self.data_dir.join(FAILED_ITEMS_FILE_NAME)
+  }
+
+  pub fn page_cache_dir(&self) -> PathBuf {
+    self.data_dir.join(PAGE_CACHE_DIR_NAME)
+  }
+
+  /// The compressed copy of a thread page, kept in the directory the media template puts the thread's media in.
+  ///
+  /// Media directories depending on more than the board and thread, such as the post's date, aren't known before the page is read,
+  /// so those threads' snapshots are kept in the data directory instead.
+  pub fn thread_snapshot_path(&self, board: &str, thread_id: &str) -> PathBuf {
+    let values = TemplateValues {
+      board,
+      thread_id,
+      ..TemplateValues::default()
+    };
+    let snapshot_dir = match self
+      .media_path_template
+      .thread_dir()
+      .and_then(|thread_dir| thread_dir.render(&values).ok())
+    {
+      Some(thread_dir) => self.data_dir.join(thread_dir),
+      None => self.data_dir.clone(),
+    };
+
+    snapshot_dir.join(format!(
+      "{THREAD_SNAPSHOT_FILE_PREFIX}{}{SNAPSHOT_FILE_EXTENSION}",
+      sanitize_component(thread_id)
+    ))
+  }
+
+  /// The thread a file is the snapshot of, going by its name.
+  pub fn snapshot_thread_id(file_name: &str) -> Option<&str> {
+    file_name
+      .strip_prefix(THREAD_SNAPSHOT_FILE_PREFIX)?
+      .strip_suffix(SNAPSHOT_FILE_EXTENSION)
+      .filter(|thread_id| !thread_id.is_empty())
+  }
+
+  /// The compressed copy of a search page, kept apart for every search so profiles sharing a data directory don't replace each other's.
+  pub fn search_page_snapshot_path(&self, search_url: &str, page_number: usize) -> PathBuf {
+    let search_hash: String = Md5::digest(search_url.as_bytes())
+      .iter()
+      .map(|byte| format!("{byte:02x}"))
+      .collect();
+
+    self
+      .data_dir
+      .join(SEARCH_PAGE_SNAPSHOT_DIR_NAME)
+      .join(search_hash)
+      .join(format!("{page_number}{SNAPSHOT_FILE_EXTENSION}"))
+  }
+
+  pub fn quarantine_dir(&self) -> PathBuf {
+    self.data_dir.join(QUARANTINE_DIR_NAME)
+  }
+}
+
+impl Default for Storage {
+  fn default() -> Self {
+    StorageSettings::default()
+      .try_into()
+      .expect("The default storage settings are always valid.")
+  }
+}
+
+impl TryFrom<StorageSettings> for Storage {
+  type Error = ScrapeError;
+
+  fn try_from(settings: StorageSettings) -> Result<Self, Self::Error> {
+    Ok(Self {
+      data_dir: settings.data_dir,
+      media_path_template: PathTemplate::parse(&settings.media_path_template)?,
+      hyperlink_path_template: PathTemplate::parse(&settings.hyperlink_path_template)?,
+      metadata_path_template: PathTemplate::parse(&settings.metadata_path_template)?,
+      hard_link_duplicate_media: settings.hard_link_duplicate_media,
+    })
+  }
+}
+
+impl Default for StorageSettings {
+  fn default() -> Self {
+    Self {
+      data_dir: PathBuf::from(DEFAULT_DATA_DESTINATION_DIR),
+      media_path_template: DEFAULT_MEDIA_PATH_FORMAT.to_string(),
+      hyperlink_path_template: DEFAULT_HYPERLINK_PATH_FORMAT.to_string(),
+      metadata_path_template: DEFAULT_METADATA_PATH_FORMAT.to_string(),
+      hard_link_duplicate_media: true,
+    }
+  }
+}
@@ -0,0 +1,89 @@
+use crate::error::ScrapeResult;
+use reqwest::Url;
+use std::collections::HashSet;
+use std::io::BufRead;
+
+/// A thread named in a thread list, along with the board it's on if the line said.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThreadReference {
+  pub board: Option<String>,
+  pub thread_id: String,
+}
+
+/// Reads a thread from a line, which can be a bare thread ID or the thread's URL on an archive or a live board.
+///
+/// example:
+/// ```text
+/// 70000001
+/// https://archive.palanq.win/vt/thread/70000001/#70000005
+/// https://boards.4chan.org/vt/thread/70000001/some-subject
+/// ```
+pub fn parse_thread_reference(line: &str) -> Option<ThreadReference> {
+  let line = line.trim();
+
+  if is_thread_id(line) {
+    return Some(ThreadReference {
+      board: None,
+      thread_id: line.to_string(),
+    });
+  }
+
+  let url = Url::parse(line).ok()?;
+  let path_segments: Vec<&str> = url.path_segments()?.collect();
+  let thread_position = path_segments
+    .windows(2)
+    .position(|segments| segments[0] == "thread" && is_thread_id(segments[1]))?;
+
+  Some(ThreadReference {
+    board: thread_position
+      .checked_sub(1)
+      .map(|board_position| path_segments[board_position].to_string()),
+    thread_id: path_segments[thread_position + 1].to_string(),
+  })
+}
+
+/// Reads the thread ID on each line, skipping blank lines, `#` comments and repeats.
+///
+/// Lines that aren't threads, or are threads on a board other than `board`, are logged and skipped.
+///
+/// # Errors
+/// - The list couldn't be read.
+pub fn read_thread_list(reader: impl BufRead, board: &str) -> ScrapeResult<Vec<String>> {
+  let mut seen_thread_ids = HashSet::new();
+  let mut thread_ids = vec![];
+
+  for line in reader.lines() {
+    let line = line?;
+    let line = line.trim();
+
+    if line.is_empty() || line.starts_with('#') {
+      continue;
+    }
+
+    let Some(thread_reference) = parse_thread_reference(line) else {
+      tracing::warn!("Skipping {line:?} in the thread list, it isn't a thread ID or URL.");
+
+      continue;
+    };
+
+    if let Some(line_board) = thread_reference.board.as_deref() {
+      if line_board != board {
+        tracing::warn!(
+          "Skipping {line:?} in the thread list, it's on /{line_board}/ rather than /{board}/."
+        );
+
+        continue;
+      }
+    }
+
+    if seen_thread_ids.insert(thread_reference.thread_id.clone()) {
+      thread_ids.push(thread_reference.thread_id);
+    }
+  }
+
+  Ok(thread_ids)
+}
+
+fn is_thread_id(value: &str) -> bool {
+  !value.is_empty() && value.bytes().all(|byte| byte.is_ascii_digit())
+}
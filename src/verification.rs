@@ -0,0 +1,74 @@
+use crate::error::ScrapeResult;
+use md5::{Digest, Md5};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The result of checking downloaded media against what the archive and server advertised.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Verification {
+  /// The MD5 matched, and the size if one was advertised.
+  Verified,
+  /// No MD5 was advertised, but the size matched.
+  SizeMatched,
+  /// Nothing was advertised to check against.
+  Unverified,
+  Mismatched {
+    expected_md5: Option<String>,
+    actual_md5: String,
+    expected_size: Option<u64>,
+    actual_size: u64,
+  },
+}
+
+impl Verification {
+  /// Compares the bytes against the expected hex encoded MD5 and size.
+  pub fn check(bytes: &[u8], expected_md5: Option<&str>, expected_size: Option<u64>) -> Self {
+    let actual_md5: String = Md5::digest(bytes)
+      .iter()
+      .map(|byte| format!("{byte:02x}"))
+      .collect();
+    let actual_size = bytes.len() as u64;
+
+    let md5_matches =
+      expected_md5.map(|expected_md5| expected_md5.eq_ignore_ascii_case(&actual_md5));
+    let size_matches = expected_size.map(|expected_size| expected_size == actual_size);
+
+    match (md5_matches, size_matches) {
+      (Some(false), _) | (_, Some(false)) => Self::Mismatched {
+        expected_md5: expected_md5.map(str::to_string),
+        actual_md5,
+        expected_size,
+        actual_size,
+      },
+      (Some(true), _) => Self::Verified,
+      (None, Some(true)) => Self::SizeMatched,
+      (None, None) => Self::Unverified,
+    }
+  }
+
+  pub fn is_mismatched(&self) -> bool {
+    matches!(self, Self::Mismatched { .. })
+  }
+}
+
+/// Moves bytes that failed verification out of the way into the quarantine directory.
+///
+/// # Errors
+/// - The quarantine directory couldn't be created, or the file couldn't be written.
+pub fn quarantine_media(
+  quarantine_dir: &Path,
+  bytes: &[u8],
+  file_name: &str,
+) -> ScrapeResult<PathBuf> {
+  if !quarantine_dir.exists() {
+    tracing::info!("Creating the quarantine directory.");
+    fs::create_dir_all(quarantine_dir)?;
+  }
+
+  let quarantine_path = quarantine_dir.join(file_name);
+  fs::write(&quarantine_path, bytes)?;
+
+  Ok(quarantine_path)
+}
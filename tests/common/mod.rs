@@ -0,0 +1,88 @@
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+use thread_archive_scraper::backend::ArchiveBackend;
+use thread_archive_scraper::storage::Storage;
+use thread_archive_scraper::{Scraper, ScraperBuilder};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+pub const THREAD_ID: &str = "70000001";
+pub const FIRST_MEDIA_BYTES: &[u8] = b"first media bytes";
+pub const SECOND_MEDIA_BYTES: &[u8] = b"second media bytes";
+
+/// Reads a fixture from `tests/fixtures`, pointing any `{{server}}` URLs at the given mock server.
+pub fn fixture(name: &str, server_uri: &str) -> String {
+  let fixture_path = Path::new(env!("CARGO_MANIFEST_DIR"))
+    .join("tests/fixtures")
+    .join(name);
+
+  fs::read_to_string(fixture_path)
+    .unwrap()
+    .replace("{{server}}", server_uri)
+}
+
+pub fn backend(server: &MockServer) -> ArchiveBackend {
+  ArchiveBackend {
+    search_url: format!("{}/vt/search/subject/%2Fshon%2F", server.uri()),
+    thread_url: format!("{}/vt/thread/", server.uri()),
+    board: "vt".to_string(),
+  }
+}
+
+pub fn storage(data_dir: &TempDir) -> Storage {
+  Storage {
+    data_dir: data_dir.path().to_path_buf(),
+    ..Storage::default()
+  }
+}
+
+/// A builder scraping from the mock server into the temporary directory.
+pub fn scraper_builder(server: &MockServer, data_dir: &TempDir) -> ScraperBuilder {
+  Scraper::builder()
+    .backend(backend(server))
+    .storage(storage(data_dir))
+}
+
+/// Serves the fixture thread and the media it links to.
+pub async fn mount_thread(server: &MockServer) {
+  Mock::given(method("GET"))
+    .and(path(format!("/vt/thread/{THREAD_ID}")))
+    .respond_with(ResponseTemplate::new(200).set_body_string(fixture("thread.html", &server.uri())))
+    .mount(server)
+    .await;
+  mount_media(server, "/media/1709294400001.png", FIRST_MEDIA_BYTES).await;
+  mount_media(server, "/media/1709295000002.webm", SECOND_MEDIA_BYTES).await;
+  mount_media(server, "/media/1709295600003.png", FIRST_MEDIA_BYTES).await;
+}
+
+pub async fn mount_media(server: &MockServer, media_path: &str, bytes: &[u8]) {
+  Mock::given(method("GET"))
+    .and(path(media_path))
+    .respond_with(ResponseTemplate::new(200).set_body_bytes(bytes))
+    .mount(server)
+    .await;
+}
+
+/// Every file under the directory, relative to it and sorted.
+pub fn files_in(directory: &Path) -> Vec<PathBuf> {
+  let mut files = vec![];
+  let mut directories = vec![directory.to_path_buf()];
+
+  while let Some(current_directory) = directories.pop() {
+    for entry in fs::read_dir(current_directory).unwrap() {
+      let entry_path = entry.unwrap().path();
+
+      if entry_path.is_dir() {
+        directories.push(entry_path);
+      } else {
+        files.push(entry_path.strip_prefix(directory).unwrap().to_path_buf());
+      }
+    }
+  }
+
+  files.sort();
+  files
+}
@@ -0,0 +1,234 @@
+mod common;
+
+use chrono::{DateTime, Utc};
+use common::fixture;
+use scraper::{ElementRef, Html, Selector};
+use serde_json::Value;
+use std::path::Path;
+use thread_archive_scraper::html_parsing::*;
+use thread_archive_scraper::media_filter::FilterRejection;
+use thread_archive_scraper::post_filter::PostFilter;
+use thread_archive_scraper::DEFAULT_BANNED_URL_LIST;
+
+const SERVER_URI: &str = "http://archive.test";
+
+fn banned_urls() -> Vec<String> {
+  DEFAULT_BANNED_URL_LIST
+    .iter()
+    .map(|banned_url| banned_url.to_string())
+    .collect()
+}
+
+fn thread_document() -> Html {
+  Html::parse_document(&fixture("thread.html", SERVER_URI))
+}
+
+fn find_post<'a>(document: &'a Html, post_id: &str) -> ElementRef<'a> {
+  let post_selector = Selector::parse(&format!(r#"article[id="{post_id}"]"#)).unwrap();
+
+  document.select(&post_selector).next().unwrap()
+}
+
+#[test]
+fn posts_are_parsed_as_recorded() {
+  let document = thread_document();
+  let article_selector = Selector::parse("article.post").unwrap();
+  let expected_posts: Vec<Value> =
+    serde_json::from_str(&fixture("thread_posts.json", SERVER_URI)).unwrap();
+  let posts: Vec<_> = document.select(&article_selector).collect();
+
+  assert_eq!(posts.len(), expected_posts.len());
+
+  for (post, expected) in posts.iter().zip(&expected_posts) {
+    let post_id = post.value().id().unwrap();
+    assert_eq!(post_id, expected["post_id"]);
+
+    let timestamp = extract_post_timestamp(post).unwrap();
+    let expected_timestamp: DateTime<Utc> =
+      expected["timestamp"].as_str().unwrap().parse().unwrap();
+    assert_eq!(timestamp, expected_timestamp, "post {post_id}");
+
+    let poster = extract_poster_details(post);
+    let expected_poster = &expected["poster"];
+    assert_eq!(
+      poster,
+      PosterDetails {
+        name: expected_poster["name"].as_str().map(str::to_string),
+        tripcode: expected_poster["tripcode"].as_str().map(str::to_string),
+        poster_id: expected_poster["poster_id"].as_str().map(str::to_string),
+      },
+      "post {post_id}"
+    );
+
+    let reply_targets = extract_reply_targets(post).unwrap();
+    let expected_reply_targets: Vec<String> =
+      serde_json::from_value(expected["replies_to"].clone()).unwrap();
+    assert_eq!(reply_targets, expected_reply_targets, "post {post_id}");
+
+    let hyperlinks = extract_hyperlinks_from_post(post, &banned_urls()).unwrap();
+    let expected_hyperlinks: Vec<String> =
+      serde_json::from_value(expected["hyperlinks"].clone()).unwrap();
+    assert_eq!(hyperlinks, expected_hyperlinks, "post {post_id}");
+
+    let media = extract_media_url_from_post(post);
+    let expected_media = &expected["media"];
+
+    let Some(media) = media else {
+      assert!(expected_media.is_null(), "post {post_id} is missing media");
+      continue;
+    };
+
+    assert_eq!(media.url, expected_media["url"], "post {post_id}");
+    assert_eq!(
+      media.extension, expected_media["extension"],
+      "post {post_id}"
+    );
+    assert_eq!(
+      media.original_name.as_deref(),
+      expected_media["original_name"].as_str(),
+      "post {post_id}"
+    );
+    assert_eq!(
+      media.md5.as_deref(),
+      expected_media["md5"].as_str(),
+      "post {post_id}"
+    );
+    assert_eq!(
+      media.advertised_size,
+      expected_media["advertised_size"].as_u64(),
+      "post {post_id}"
+    );
+    assert_eq!(
+      media.dimensions,
+      serde_json::from_value(expected_media["dimensions"].clone()).unwrap(),
+      "post {post_id}"
+    );
+  }
+}
+
+#[test]
+fn banned_hyperlinks_are_left_out() {
+  let document = thread_document();
+  let post = find_post(&document, "70000002");
+
+  let unfiltered = extract_hyperlinks_from_post(&post, &[]).unwrap();
+  assert_eq!(
+    unfiltered,
+    vec![
+      "https://pomf2.lain.la/f/abc123.png",
+      "https://www.youtube.com/watch?v=dQw4w9WgXcQ"
+    ]
+  );
+
+  let filtered = extract_hyperlinks_from_post(&post, &["lain.".to_string()]).unwrap();
+  assert_eq!(
+    filtered,
+    vec!["https://www.youtube.com/watch?v=dQw4w9WgXcQ"]
+  );
+}
+
+#[test]
+fn post_filters_check_text_poster_date_and_replies() {
+  let document = thread_document();
+  let post_filter =
+    |profile_section: &str| -> PostFilter { toml::from_str(profile_section).unwrap() };
+  let rejections = |post_filter: &PostFilter| -> Vec<Option<FilterRejection>> {
+    ["70000002", "70000003", "70000004", "70000005"]
+      .into_iter()
+      .map(|post_id| post_filter.check(&find_post(&document, post_id)).err())
+      .collect()
+  };
+
+  assert_eq!(
+    rejections(&post_filter(
+      r#"text_patterns = ["(?i)^audio"]
+keywords = ["REPOST"]"#
+    )),
+    [
+      Some(FilterRejection::PostText),
+      None,
+      Some(FilterRejection::PostText),
+      None
+    ]
+  );
+  assert_eq!(
+    rejections(&post_filter(r#"tripcodes = ["!!cL1pPeRtRp"]"#)),
+    [
+      Some(FilterRejection::Poster),
+      Some(FilterRejection::Poster),
+      None,
+      Some(FilterRejection::Poster)
+    ]
+  );
+  assert_eq!(
+    rejections(&post_filter(
+      r#"posted_after = "2024-03-01T12:05:00Z"
+posted_before = "2024-03-01T12:15:00Z""#
+    )),
+    [
+      Some(FilterRejection::PostDate),
+      None,
+      None,
+      Some(FilterRejection::PostDate)
+    ]
+  );
+  assert_eq!(
+    rejections(&post_filter(r#"replies_to = ["70000004"]"#)),
+    [
+      Some(FilterRejection::NotAReply),
+      Some(FilterRejection::NotAReply),
+      Some(FilterRejection::NotAReply),
+      None
+    ]
+  );
+  assert!(toml::from_str::<PostFilter>(r#"text_patterns = ["("]"#).is_err());
+}
+
+#[test]
+fn posts_without_media_have_none() {
+  let document = thread_document();
+  let post = find_post(&document, "70000003");
+
+  assert!(extract_media_url_from_post(&post).is_none());
+}
+
+#[test]
+fn find_child_with_class_only_checks_direct_children() {
+  let document = Html::parse_fragment(
+    r#"<div id="root"><div class="outer"><span class="inner"></span></div><p class="inner second"></p></div>"#,
+  );
+  let root = document
+    .select(&Selector::parse("#root").unwrap())
+    .next()
+    .unwrap();
+
+  let outer = find_child_with_class(&root, "outer").unwrap();
+  assert_eq!(outer.value().name(), "div");
+
+  let inner = find_child_with_class(&root, "inner").unwrap();
+  assert_eq!(inner.value().name(), "p");
+
+  assert!(find_child_with_class(&root, "missing").is_none());
+  assert!(find_child_with_class(&outer, "second").is_none());
+}
+
+#[test]
+fn find_child_with_tag_only_checks_direct_children() {
+  let document =
+    Html::parse_fragment(r#"<div id="root"><div><time></time></div><span></span></div>"#);
+  let root = document
+    .select(&Selector::parse("#root").unwrap())
+    .next()
+    .unwrap();
+
+  assert!(find_child_with_tag(&root, "span").is_some());
+  assert!(find_child_with_tag(&root, "time").is_none());
+}
+
+#[test]
+fn part_files_keep_the_media_name() {
+  assert_eq!(
+    part_file_path(Path::new("data/70000001/70000001-70000002-.png")),
+    Path::new("data/70000001/70000001-70000002-.png.part")
+  );
+}
@@ -0,0 +1,97 @@
+use reqwest::StatusCode;
+use std::fs;
+use tempfile::TempDir;
+use thread_archive_scraper::failed_items::{ErrorClass, FailedQueue, FailedTarget};
+use thread_archive_scraper::ScrapeError;
+
+fn failed_thread(thread_id: &str) -> FailedTarget {
+  FailedTarget::Thread {
+    thread_id: thread_id.to_string(),
+  }
+}
+
+fn http_status(status: StatusCode) -> ScrapeError {
+  ScrapeError::HttpStatus {
+    url: "https://archive.example/vt/thread/70000001".to_string(),
+    status,
+  }
+}
+
+#[test]
+fn items_outlive_a_run_that_never_saved() {
+  let data_dir = TempDir::new().unwrap();
+  let queue_file_path = data_dir.path().join("nested/failed_items.jsonl");
+
+  let failed_items = FailedQueue::load(&queue_file_path).unwrap();
+  failed_items.record(
+    failed_thread("70000001"),
+    &http_status(StatusCode::SERVICE_UNAVAILABLE),
+  );
+  failed_items.record(
+    failed_thread("70000002"),
+    &http_status(StatusCode::NOT_FOUND),
+  );
+  failed_items.record(
+    failed_thread("70000001"),
+    &http_status(StatusCode::NOT_FOUND),
+  );
+  // Taken for a retry that's killed before it finishes.
+  failed_items.take_matching(&[]);
+  drop(failed_items);
+
+  let reloaded = FailedQueue::load(&queue_file_path).unwrap();
+  let reloaded_items = reloaded.take_matching(&[]);
+
+  assert_eq!(reloaded_items.len(), 2);
+  assert!(reloaded_items
+    .iter()
+    .all(|failed_item| failed_item.error_class == ErrorClass::ClientError));
+}
+
+#[test]
+fn saving_rewrites_the_file_with_what_is_left() {
+  let data_dir = TempDir::new().unwrap();
+  let queue_file_path = data_dir.path().join("failed_items.jsonl");
+
+  let failed_items = FailedQueue::load(&queue_file_path).unwrap();
+  failed_items.record(
+    failed_thread("70000001"),
+    &http_status(StatusCode::SERVICE_UNAVAILABLE),
+  );
+  failed_items.record(
+    failed_thread("70000002"),
+    &http_status(StatusCode::NOT_FOUND),
+  );
+  failed_items.take_matching(&[ErrorClass::ServerError]);
+  failed_items.save().unwrap();
+
+  assert_eq!(
+    fs::read_to_string(&queue_file_path)
+      .unwrap()
+      .lines()
+      .count(),
+    1
+  );
+
+  failed_items.take_matching(&[]);
+  failed_items.save().unwrap();
+
+  assert!(!queue_file_path.exists());
+}
+
+#[test]
+fn queues_without_appending_leave_the_file_alone() {
+  let data_dir = TempDir::new().unwrap();
+  let queue_file_path = data_dir.path().join("failed_items.jsonl");
+
+  let failed_items = FailedQueue::load(&queue_file_path)
+    .unwrap()
+    .without_appending();
+  failed_items.record(
+    failed_thread("70000001"),
+    &http_status(StatusCode::NOT_FOUND),
+  );
+
+  assert_eq!(failed_items.len(), 1);
+  assert!(!queue_file_path.exists());
+}
@@ -0,0 +1,94 @@
+use mock_archive::{Archive, ArchiveSettings, Faults, RunningArchive};
+use tempfile::TempDir;
+use thread_archive_scraper::backend::ArchiveBackend;
+use thread_archive_scraper::progress::Stat;
+use thread_archive_scraper::storage::Storage;
+use thread_archive_scraper::{ScrapeError, Scraper};
+
+fn small_archive() -> Archive {
+  Archive::generate(ArchiveSettings {
+    seed: 7,
+    thread_count: 3,
+    posts_per_thread: 8,
+    threads_per_page: 2,
+    ..ArchiveSettings::default()
+  })
+}
+
+fn scraper(running_archive: &RunningArchive, data_dir: &TempDir) -> Scraper {
+  Scraper::builder()
+    .backend(ArchiveBackend {
+      search_url: running_archive.search_url(),
+      thread_url: running_archive.thread_url(),
+      board: running_archive.archive().settings.board.clone(),
+    })
+    .storage(Storage {
+      data_dir: data_dir.path().to_path_buf(),
+      ..Storage::default()
+    })
+    .build()
+    .unwrap()
+}
+
+/// Media in replies, the opening post's media isn't scraped.
+fn reply_media_count(archive: &Archive) -> u64 {
+  archive
+    .threads
+    .iter()
+    .flat_map(|thread| &thread.posts[1..])
+    .filter(|post| post.media.is_some())
+    .count() as u64
+}
+
+#[tokio::test]
+async fn every_thread_of_a_generated_archive_is_scraped() {
+  let running_archive = mock_archive::spawn(small_archive(), Faults::default())
+    .await
+    .unwrap();
+  let data_dir = TempDir::new().unwrap();
+  let scraper = scraper(&running_archive, &data_dir);
+
+  let page_count = running_archive.archive().page_count();
+  let stopped_state = scraper.scrape_page_range(1..=page_count).await;
+
+  assert!(stopped_state.is_none());
+
+  let run_stats = scraper.run_stats();
+  let media_count = reply_media_count(running_archive.archive());
+  assert_eq!(run_stats.get(Stat::PageDone), page_count as u64);
+  assert_eq!(run_stats.get(Stat::ThreadDone), 3);
+  assert_eq!(run_stats.get(Stat::ThreadFailed), 0);
+  assert_eq!(run_stats.get(Stat::MediaQueued), media_count);
+  assert_eq!(
+    run_stats.get(Stat::MediaDownloaded) + run_stats.get(Stat::MediaSkipped),
+    media_count
+  );
+  assert_eq!(run_stats.get(Stat::MediaFailed), 0);
+}
+
+#[tokio::test]
+async fn truncated_media_fails_the_download() {
+  let running_archive = mock_archive::spawn(
+    small_archive(),
+    Faults {
+      truncate_chance: 1.0,
+      ..Faults::default()
+    },
+  )
+  .await
+  .unwrap();
+  let data_dir = TempDir::new().unwrap();
+  let scraper = scraper(&running_archive, &data_dir);
+  let thread = running_archive
+    .archive()
+    .threads
+    .iter()
+    .find(|thread| thread.posts[1..].iter().any(|post| post.media.is_some()))
+    .unwrap();
+
+  let error = scraper.scrape_thread(&thread.id).await.unwrap_err();
+
+  assert!(matches!(error, ScrapeError::Network { .. }));
+  assert_eq!(scraper.run_stats().get(Stat::MediaFailed), 1);
+  assert!(running_archive.stats().truncated >= 1);
+}
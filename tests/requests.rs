@@ -0,0 +1,485 @@
+use reqwest::StatusCode;
+use std::net::TcpListener;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use thread_archive_scraper::bandwidth::BandwidthSettings;
+use thread_archive_scraper::helper_methods::{get_bytes_with_retry, get_with_retry};
+use thread_archive_scraper::http_client::{HttpClient, HttpSettings};
+use thread_archive_scraper::ratelimiter::DeviationRateLimiter;
+use thread_archive_scraper::{ScrapeError, BASE_RATE_LIMIT_DURATION};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+async fn get(server: &MockServer, retry_count: usize) -> Result<reqwest::Response, ScrapeError> {
+  get_with_retry(
+    &HttpClient::default(),
+    format!("{}/page", server.uri()),
+    retry_count,
+    &DeviationRateLimiter::new().unwrap(),
+    Duration::ZERO,
+  )
+  .await
+}
+
+/// Requests sent to the server, other than for its robots.txt.
+async fn request_count(server: &MockServer) -> usize {
+  server
+    .received_requests()
+    .await
+    .unwrap()
+    .iter()
+    .filter(|request| request.url.path() != "/robots.txt")
+    .count()
+}
+
+#[tokio::test]
+async fn server_errors_are_retried_until_success() {
+  let server = MockServer::start().await;
+  Mock::given(method("GET"))
+    .and(path("/page"))
+    .respond_with(ResponseTemplate::new(500))
+    .up_to_n_times(2)
+    .with_priority(1)
+    .mount(&server)
+    .await;
+  Mock::given(method("GET"))
+    .and(path("/page"))
+    .respond_with(ResponseTemplate::new(200).set_body_string("ok"))
+    .mount(&server)
+    .await;
+
+  let response = get(&server, 5).await.unwrap();
+
+  assert_eq!(response.text().await.unwrap(), "ok");
+  assert_eq!(request_count(&server).await, 3);
+}
+
+#[tokio::test]
+async fn retries_give_up_after_the_retry_count() {
+  let server = MockServer::start().await;
+  Mock::given(method("GET"))
+    .and(path("/page"))
+    .respond_with(ResponseTemplate::new(503))
+    .mount(&server)
+    .await;
+
+  let error = get(&server, 3).await.unwrap_err();
+
+  assert!(matches!(
+    error,
+    ScrapeError::HttpStatus {
+      status: StatusCode::SERVICE_UNAVAILABLE,
+      ..
+    }
+  ));
+  assert_eq!(request_count(&server).await, 3);
+}
+
+#[tokio::test]
+async fn client_errors_are_not_retried() {
+  let server = MockServer::start().await;
+  Mock::given(method("GET"))
+    .respond_with(ResponseTemplate::new(404))
+    .mount(&server)
+    .await;
+
+  let error = get(&server, 5).await.unwrap_err();
+
+  assert!(matches!(
+    error,
+    ScrapeError::HttpStatus {
+      status: StatusCode::NOT_FOUND,
+      ..
+    }
+  ));
+  assert!(!error.is_retryable());
+  assert_eq!(request_count(&server).await, 1);
+}
+
+#[tokio::test]
+async fn rate_limited_responses_are_retried_and_reported() {
+  let server = MockServer::start().await;
+  Mock::given(method("GET"))
+    .respond_with(ResponseTemplate::new(429))
+    .mount(&server)
+    .await;
+
+  let error = get(&server, 2).await.unwrap_err();
+
+  assert!(error.is_rate_limited());
+  assert_eq!(request_count(&server).await, 2);
+}
+
+#[tokio::test]
+async fn unreachable_hosts_are_network_errors() {
+  let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+  let unreachable_url = format!("http://{}/page", listener.local_addr().unwrap());
+  drop(listener);
+
+  let error = get_with_retry(
+    &HttpClient::default(),
+    unreachable_url,
+    2,
+    &DeviationRateLimiter::new().unwrap(),
+    Duration::ZERO,
+  )
+  .await
+  .unwrap_err();
+
+  assert!(matches!(error, ScrapeError::Network { .. }));
+}
+
+#[tokio::test]
+async fn rate_limiter_spaces_out_bursts() {
+  let rate_limiter = DeviationRateLimiter::new().unwrap();
+  let start = Instant::now();
+
+  // The limiter starts empty and refills 4 tokens per interval, so a 5th request needs a second refill.
+  for _ in 0..5 {
+    rate_limiter.wait().await;
+  }
+
+  assert!(start.elapsed() >= BASE_RATE_LIMIT_DURATION * 2);
+}
+
+#[tokio::test]
+async fn profile_http_settings_rotate_user_agents_and_keep_cookies() {
+  let server = MockServer::start().await;
+  Mock::given(method("GET"))
+    .and(header("accept-language", "en-US"))
+    .respond_with(
+      ResponseTemplate::new(200).insert_header("set-cookie", "session=abc; Path=/; Max-Age=3600"),
+    )
+    .mount(&server)
+    .await;
+  let cookie_jar_dir = tempfile::tempdir().unwrap();
+  let cookie_jar = cookie_jar_dir.path().join("cookies.json");
+  let settings: HttpSettings = toml::from_str(&format!(
+    r#"
+    user_agents = ["first-agent", "second-agent"]
+    cookie_jar = {cookie_jar:?}
+    connect_timeout_secs = 5
+
+    [headers]
+    Accept-Language = "en-US"
+    "#
+  ))
+  .unwrap();
+  let client = settings.build_client().unwrap();
+
+  for _ in 0..3 {
+    client.get(&server.uri()).send().await.unwrap();
+  }
+  client.save_cookies().unwrap();
+
+  let requests = server.received_requests().await.unwrap();
+  let user_agents: Vec<&str> = requests
+    .iter()
+    .map(|request| request.headers["user-agent"].to_str().unwrap())
+    .collect();
+
+  assert_eq!(user_agents, ["first-agent", "second-agent", "first-agent"]);
+  assert_eq!(requests[1].headers["cookie"], "session=abc");
+  assert!(std::fs::read_to_string(&cookie_jar)
+    .unwrap()
+    .contains("abc"));
+
+  let reloaded_client = settings.build_client().unwrap();
+  reloaded_client.get(&server.uri()).send().await.unwrap();
+  let requests = server.received_requests().await.unwrap();
+
+  assert_eq!(requests[3].headers["cookie"], "session=abc");
+}
+
+/// Serves a body that stalls after its first few bytes on the first connection, and in full after that.
+async fn spawn_stalling_server(body: &'static [u8]) -> String {
+  let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+  let address = listener.local_addr().unwrap();
+
+  tokio::spawn(async move {
+    for connection in 0.. {
+      let (mut stream, _) = listener.accept().await.unwrap();
+      let mut request = [0; 1024];
+      let _ = stream.read(&mut request).await;
+      let headers = format!(
+        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+      );
+      stream.write_all(headers.as_bytes()).await.unwrap();
+
+      if connection == 0 {
+        stream.write_all(&body[..4]).await.unwrap();
+        tokio::spawn(async move {
+          tokio::time::sleep(Duration::from_secs(30)).await;
+          drop(stream);
+        });
+      } else {
+        stream.write_all(body).await.unwrap();
+      }
+    }
+  });
+
+  format!("http://{address}/media.png")
+}
+
+/// Serves a body that stalls on the first connection, and 503s after that, counting the connections.
+async fn spawn_stalling_then_failing_server() -> (String, Arc<AtomicUsize>) {
+  let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+  let address = listener.local_addr().unwrap();
+  let connections = Arc::new(AtomicUsize::new(0));
+  let counted_connections = connections.clone();
+
+  tokio::spawn(async move {
+    loop {
+      let (mut stream, _) = listener.accept().await.unwrap();
+      let mut request = [0; 1024];
+      let _ = stream.read(&mut request).await;
+
+      if counted_connections.fetch_add(1, Ordering::SeqCst) == 0 {
+        stream
+          .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2048\r\nConnection: close\r\n\r\nPNG!")
+          .await
+          .unwrap();
+        tokio::spawn(async move {
+          tokio::time::sleep(Duration::from_secs(30)).await;
+          drop(stream);
+        });
+      } else {
+        stream
+          .write_all(
+            b"HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+          )
+          .await
+          .unwrap();
+      }
+    }
+  });
+
+  (format!("http://{address}/media.png"), connections)
+}
+
+#[tokio::test]
+async fn stalled_transfers_are_aborted_and_retried() {
+  let body: &[u8] = &[7; 2048];
+  let url = spawn_stalling_server(body).await;
+  let settings: HttpSettings = toml::from_str(
+    r#"
+    min_bytes_per_sec = 512
+    stall_window_secs = 1
+    ignore_robots_txt = true
+    "#,
+  )
+  .unwrap();
+  let client = settings.build_client().unwrap();
+  let rate_limiter = DeviationRateLimiter::new().unwrap();
+
+  let started_at = Instant::now();
+  let (bytes, expected_size) = get_bytes_with_retry(
+    &client,
+    url.clone(),
+    2,
+    &rate_limiter,
+    Duration::ZERO,
+    |_| Ok(()),
+  )
+  .await
+  .unwrap();
+
+  assert_eq!(bytes.as_ref(), body);
+  assert_eq!(expected_size, Some(2048));
+  assert!(started_at.elapsed() < Duration::from_secs(10));
+
+  let error = client
+    .read_body(
+      client
+        .get(&spawn_stalling_server(body).await)
+        .send()
+        .await
+        .unwrap(),
+    )
+    .await
+    .unwrap_err();
+
+  assert!(matches!(error, ScrapeError::Stalled { received: 4, .. }));
+  assert!(error.is_retryable());
+}
+
+#[tokio::test]
+async fn media_downloads_share_one_retry_count_between_requests_and_bodies() {
+  let (url, connections) = spawn_stalling_then_failing_server().await;
+  let settings: HttpSettings = toml::from_str(
+    r#"
+    min_bytes_per_sec = 512
+    stall_window_secs = 1
+    ignore_robots_txt = true
+    circuit_failure_threshold = 100
+    "#,
+  )
+  .unwrap();
+
+  let error = get_bytes_with_retry(
+    &settings.build_client().unwrap(),
+    url,
+    3,
+    &DeviationRateLimiter::new().unwrap(),
+    Duration::ZERO,
+    |_| Ok(()),
+  )
+  .await
+  .unwrap_err();
+
+  assert!(matches!(
+    error,
+    ScrapeError::HttpStatus {
+      status: StatusCode::SERVICE_UNAVAILABLE,
+      ..
+    }
+  ));
+  assert_eq!(connections.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn robots_txt_disallows_paths_and_spaces_out_requests_by_its_crawl_delay() {
+  let server = MockServer::start().await;
+  Mock::given(method("GET"))
+    .and(path("/robots.txt"))
+    .respond_with(ResponseTemplate::new(200).set_body_string(
+      "User-agent: *\nDisallow: /private\nCrawl-delay: 1\n\nUser-agent: other-bot\nDisallow: /\n",
+    ))
+    .mount(&server)
+    .await;
+  Mock::given(method("GET"))
+    .respond_with(ResponseTemplate::new(200))
+    .mount(&server)
+    .await;
+  let client = HttpClient::default();
+  let rate_limiter = DeviationRateLimiter::new().unwrap();
+  let get = |page: &str| {
+    get_with_retry(
+      &client,
+      format!("{}/{page}", server.uri()),
+      1,
+      &rate_limiter,
+      Duration::ZERO,
+    )
+  };
+
+  let error = get("private/page").await.unwrap_err();
+  assert!(matches!(error, ScrapeError::Disallowed { .. }));
+
+  let started_at = Instant::now();
+  get("public/1").await.unwrap();
+  get("public/2").await.unwrap();
+
+  assert!(started_at.elapsed() >= Duration::from_millis(950));
+
+  let requested_paths: Vec<String> = server
+    .received_requests()
+    .await
+    .unwrap()
+    .iter()
+    .map(|request| request.url.path().to_string())
+    .collect();
+  assert_eq!(requested_paths, ["/robots.txt", "/public/1", "/public/2"]);
+}
+
+#[tokio::test]
+async fn robots_txt_server_errors_fail_the_attempt_and_are_fetched_again() {
+  let server = MockServer::start().await;
+  Mock::given(method("GET"))
+    .and(path("/robots.txt"))
+    .respond_with(ResponseTemplate::new(503))
+    .up_to_n_times(2)
+    .mount(&server)
+    .await;
+  Mock::given(method("GET"))
+    .and(path("/robots.txt"))
+    .respond_with(ResponseTemplate::new(404))
+    .mount(&server)
+    .await;
+  Mock::given(method("GET"))
+    .respond_with(ResponseTemplate::new(200))
+    .mount(&server)
+    .await;
+  let client = HttpClient::default();
+  let rate_limiter = DeviationRateLimiter::new().unwrap();
+  let get = |page: &str, retry_count: usize| {
+    get_with_retry(
+      &client,
+      format!("{}/{page}", server.uri()),
+      retry_count,
+      &rate_limiter,
+      Duration::ZERO,
+    )
+  };
+
+  let error = get("page/1", 1).await.unwrap_err();
+  assert!(matches!(
+    error,
+    ScrapeError::HttpStatus {
+      status: StatusCode::SERVICE_UNAVAILABLE,
+      ..
+    }
+  ));
+
+  get("page/2", 2).await.unwrap();
+  get("page/3", 1).await.unwrap();
+
+  let requested_paths: Vec<String> = server
+    .received_requests()
+    .await
+    .unwrap()
+    .iter()
+    .map(|request| request.url.path().to_string())
+    .collect();
+  assert_eq!(
+    requested_paths,
+    [
+      "/robots.txt",
+      "/robots.txt",
+      "/robots.txt",
+      "/page/2",
+      "/page/3"
+    ]
+  );
+}
+
+#[tokio::test]
+async fn media_bodies_are_throttled_by_caps_that_can_change_at_runtime() {
+  let server = MockServer::start().await;
+  Mock::given(method("GET"))
+    .respond_with(ResponseTemplate::new(200).set_body_bytes(vec![1; 16 * 1024]))
+    .mount(&server)
+    .await;
+  let client = HttpClient::default();
+  let media_url = format!("{}/media.webm", server.uri());
+  let timed_download = || async {
+    let started_at = Instant::now();
+    let response = client.get(&media_url).send().await.unwrap();
+    let body = client.read_body(response).await.unwrap();
+
+    assert_eq!(body.len(), 16 * 1024);
+
+    started_at.elapsed()
+  };
+
+  client.bandwidth_limiter().apply(&BandwidthSettings {
+    max_bytes_per_sec: Some(16 * 1024),
+    ..BandwidthSettings::default()
+  });
+  assert!(timed_download().await >= Duration::from_millis(900));
+
+  client
+    .bandwidth_limiter()
+    .apply(&BandwidthSettings::default());
+  assert!(timed_download().await < Duration::from_millis(500));
+
+  let server_host = server.uri().trim_start_matches("http://").to_string();
+  client.bandwidth_limiter().apply(&BandwidthSettings {
+    max_bytes_per_sec: Some(1024 * 1024),
+    hosts: [(server_host, 16 * 1024)].into(),
+  });
+  assert!(timed_download().await >= Duration::from_millis(900));
+}
@@ -0,0 +1,870 @@
+mod common;
+
+use common::*;
+use serde_json::Value;
+use std::fs;
+use std::path::PathBuf;
+use std::time::SystemTime;
+use tempfile::TempDir;
+use thread_archive_scraper::disk_guard::DiskSettings;
+use thread_archive_scraper::failed_items::ErrorClass;
+use thread_archive_scraper::http_client::HttpSettings;
+use thread_archive_scraper::post_filter::PostFilter;
+use thread_archive_scraper::profile::Profile;
+use thread_archive_scraper::progress::Stat;
+use thread_archive_scraper::shutdown::ShutdownSignal;
+use thread_archive_scraper::snapshot::write_snapshot;
+use thread_archive_scraper::{ScrapeError, ScrapeEvent, Scraper};
+use wiremock::matchers::{header, method, path, path_regex};
+use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+
+fn read_json_lines(file_path: PathBuf) -> Vec<Value> {
+  fs::read_to_string(file_path)
+    .unwrap()
+    .lines()
+    .map(|line| serde_json::from_str(line).unwrap())
+    .collect()
+}
+
+/// Paths requested from the server, other than its robots.txt.
+async fn requested_paths(server: &MockServer) -> Vec<String> {
+  server
+    .received_requests()
+    .await
+    .unwrap()
+    .iter()
+    .map(|request| request.url.path().to_string())
+    .filter(|path| path != "/robots.txt")
+    .collect()
+}
+
+#[tokio::test]
+async fn thread_media_metadata_and_hyperlinks_are_saved() {
+  let server = MockServer::start().await;
+  let data_dir = TempDir::new().unwrap();
+  mount_thread(&server).await;
+
+  let scraper = scraper_builder(&server, &data_dir).build().unwrap();
+  let mut events = scraper.subscribe();
+
+  scraper.scrape_thread(THREAD_ID).await.unwrap();
+
+  assert_eq!(
+    files_in(data_dir.path()),
+    vec![
+      PathBuf::from("70000001/70000001-70000002-.png"),
+      PathBuf::from("70000001/70000001-70000004-.webm"),
+      PathBuf::from("70000001/70000001-70000005-.png"),
+      PathBuf::from("70000001/metadata.jsonl"),
+      PathBuf::from("70000001/thread_70000001.html.zst"),
+      PathBuf::from("media_index.jsonl"),
+      PathBuf::from("urls.txt"),
+    ]
+  );
+
+  let thread_dir = data_dir.path().join(THREAD_ID);
+  assert_eq!(
+    fs::read(thread_dir.join("70000001-70000002-.png")).unwrap(),
+    FIRST_MEDIA_BYTES
+  );
+  assert_eq!(
+    fs::read(thread_dir.join("70000001-70000004-.webm")).unwrap(),
+    SECOND_MEDIA_BYTES
+  );
+  assert_eq!(
+    fs::read(thread_dir.join("70000001-70000005-.png")).unwrap(),
+    FIRST_MEDIA_BYTES
+  );
+
+  let modified = fs::metadata(thread_dir.join("70000001-70000002-.png"))
+    .unwrap()
+    .modified()
+    .unwrap();
+  let posted_at: chrono::DateTime<chrono::Utc> = "2024-03-01T12:00:00Z".parse().unwrap();
+  assert_eq!(modified, SystemTime::from(posted_at));
+
+  assert_eq!(
+    fs::read_to_string(data_dir.path().join("urls.txt")).unwrap(),
+    "70000001-70000002: https://pomf2.lain.la/f/abc123.png\n70000001-70000003: https://mega.nz/file/def456\n"
+  );
+
+  let metadata = read_json_lines(thread_dir.join("metadata.jsonl"));
+  assert_eq!(metadata.len(), 3);
+  assert_eq!(metadata[0]["post_id"], "70000002");
+  assert_eq!(metadata[0]["original_filename"], "cat picture.png");
+  assert_eq!(metadata[0]["md5"], "82f2b7ce46ecb0bc76f7b70e52fbb02f");
+  assert_eq!(metadata[0]["verification"]["status"], "verified");
+  assert_eq!(metadata[2]["post_id"], "70000005");
+  assert_eq!(metadata[2]["deduplication"]["kind"], "hard_linked");
+  assert!(metadata[2]["verification"].is_null());
+
+  // The repost is deduplicated by MD5, so it's never requested.
+  assert!(!requested_paths(&server)
+    .await
+    .contains(&"/media/1709295600003.png".to_string()));
+
+  let run_stats = scraper.run_stats();
+  assert_eq!(run_stats.get(Stat::MediaQueued), 3);
+  assert_eq!(run_stats.get(Stat::MediaDownloaded), 2);
+  assert_eq!(run_stats.get(Stat::MediaSkipped), 1);
+  assert_eq!(
+    run_stats.get(Stat::BytesDownloaded),
+    (FIRST_MEDIA_BYTES.len() + SECOND_MEDIA_BYTES.len()) as u64
+  );
+
+  let mut saved_media = vec![];
+  while let Ok(event) = events.try_recv() {
+    if let ScrapeEvent::MediaSaved { post_id, .. } = event {
+      saved_media.push(post_id);
+    }
+  }
+  assert_eq!(saved_media, vec!["70000002", "70000004", "70000005"]);
+}
+
+#[tokio::test]
+async fn scrapes_can_be_spawned_as_tasks() {
+  fn assert_send_sync<T: Send + Sync>(_: &T) {}
+  fn assert_send<T: Send>(_: T) {}
+
+  let server = MockServer::start().await;
+  let data_dir = TempDir::new().unwrap();
+  mount_thread(&server).await;
+
+  let scraper = scraper_builder(&server, &data_dir).build().unwrap();
+  assert_send_sync(&scraper);
+  // Never polled, only checked.
+  assert_send(scraper.scrape_page_range(1..=1));
+  assert_send(scraper.scrape_thread_ids(&[]));
+  assert_send(scraper.scrape_thread_list(&b""[..]));
+  assert_send(scraper.retry_failed(&[]));
+  assert_send(scraper.download_file_list("file_list.txt"));
+  assert_send(scraper.replay_snapshots());
+
+  let scraper = tokio::spawn(async move {
+    scraper.scrape_thread(THREAD_ID).await.unwrap();
+
+    scraper
+  })
+  .await
+  .unwrap();
+
+  assert_eq!(scraper.run_stats().get(Stat::MediaDownloaded), 2);
+}
+
+#[tokio::test]
+async fn media_already_on_disk_is_not_downloaded_again() {
+  let server = MockServer::start().await;
+  let data_dir = TempDir::new().unwrap();
+  mount_thread(&server).await;
+
+  let scraper = scraper_builder(&server, &data_dir).build().unwrap();
+  scraper.scrape_thread(THREAD_ID).await.unwrap();
+  server.reset().await;
+  mount_thread(&server).await;
+
+  let scraper = scraper_builder(&server, &data_dir).build().unwrap();
+  scraper.scrape_thread(THREAD_ID).await.unwrap();
+
+  assert_eq!(
+    requested_paths(&server).await,
+    vec![format!("/vt/thread/{THREAD_ID}")]
+  );
+  assert_eq!(scraper.run_stats().get(Stat::MediaSkipped), 3);
+  assert_eq!(scraper.run_stats().get(Stat::MediaDownloaded), 0);
+}
+
+#[tokio::test]
+async fn dry_runs_write_nothing() {
+  let server = MockServer::start().await;
+  let data_dir = TempDir::new().unwrap();
+  mount_thread(&server).await;
+
+  let client = HttpSettings {
+    cookie_jar: Some(data_dir.path().join("cookies.json")),
+    ..HttpSettings::default()
+  }
+  .build_client()
+  .unwrap();
+
+  let scraper = scraper_builder(&server, &data_dir)
+    .client(client)
+    .dry_run(true)
+    .build()
+    .unwrap();
+  scraper.scrape_thread(THREAD_ID).await.unwrap();
+
+  assert!(files_in(data_dir.path()).is_empty());
+  assert_eq!(
+    requested_paths(&server).await,
+    vec![format!("/vt/thread/{THREAD_ID}")]
+  );
+
+  let report = scraper.download_plan().unwrap().report();
+  assert!(report.contains("70000001-70000002-.png"));
+  assert!(report.contains("70000001-70000004-.webm"));
+  assert!(report.contains("https://mega.nz/file/def456"));
+}
+
+#[tokio::test]
+async fn media_failing_verification_is_quarantined() {
+  let server = MockServer::start().await;
+  let data_dir = TempDir::new().unwrap();
+  Mock::given(method("GET"))
+    .and(path(format!("/vt/thread/{THREAD_ID}")))
+    .respond_with(ResponseTemplate::new(200).set_body_string(fixture("thread.html", &server.uri())))
+    .mount(&server)
+    .await;
+  mount_media(&server, "/media/1709294400001.png", b"corrupted bytes").await;
+
+  let scraper = scraper_builder(&server, &data_dir).build().unwrap();
+  let error = scraper.scrape_thread(THREAD_ID).await.unwrap_err();
+
+  assert!(matches!(error, ScrapeError::VerificationFailed { .. }));
+  assert!(!data_dir
+    .path()
+    .join(THREAD_ID)
+    .join("70000001-70000002-.png")
+    .exists());
+  assert_eq!(
+    fs::read_dir(data_dir.path().join("quarantine"))
+      .unwrap()
+      .count(),
+    thread_archive_scraper::MEDIA_VERIFICATION_ATTEMPTS
+  );
+
+  let metadata = read_json_lines(data_dir.path().join(THREAD_ID).join("metadata.jsonl"));
+  assert_eq!(metadata[0]["verification"]["status"], "mismatched");
+}
+
+#[tokio::test]
+async fn page_ranges_scrape_every_listed_thread() {
+  let server = MockServer::start().await;
+  let data_dir = TempDir::new().unwrap();
+  mount_thread(&server).await;
+  Mock::given(method("GET"))
+    .and(path("/vt/search/subject/%2Fshon%2F/page/1"))
+    .respond_with(
+      ResponseTemplate::new(200).set_body_string(fixture("search_page.html", &server.uri())),
+    )
+    .mount(&server)
+    .await;
+
+  let scraper = scraper_builder(&server, &data_dir).build().unwrap();
+  let stopped_state = scraper.scrape_page_range(1..=1).await;
+
+  assert!(stopped_state.is_none());
+
+  let run_stats = scraper.run_stats();
+  assert_eq!(run_stats.get(Stat::PageDone), 1);
+  assert_eq!(run_stats.get(Stat::ThreadQueued), 2);
+  // The second thread isn't served, so it 404s.
+  assert_eq!(run_stats.get(Stat::ThreadFailed), 1);
+  assert!(data_dir
+    .path()
+    .join(THREAD_ID)
+    .join("70000001-70000002-.png")
+    .exists());
+  assert!(!data_dir.path().join("scrape_state.json").exists());
+}
+
+#[tokio::test]
+async fn thread_pages_without_posts_are_markup_errors() {
+  let server = MockServer::start().await;
+  let data_dir = TempDir::new().unwrap();
+  Mock::given(method("GET"))
+    .and(path(format!("/vt/thread/{THREAD_ID}")))
+    .respond_with(
+      ResponseTemplate::new(200).set_body_string("<html><body>Maintenance</body></html>"),
+    )
+    .mount(&server)
+    .await;
+
+  let scraper = scraper_builder(&server, &data_dir).build().unwrap();
+  let error = scraper.scrape_thread(THREAD_ID).await.unwrap_err();
+
+  assert!(matches!(error, ScrapeError::Markup { .. }));
+}
+
+#[tokio::test]
+async fn media_on_a_host_that_is_down_is_deferred_to_a_later_run() {
+  let server = MockServer::start().await;
+  let media_server = MockServer::start().await;
+  let data_dir = TempDir::new().unwrap();
+  Mock::given(method("GET"))
+    .and(path(format!("/vt/thread/{THREAD_ID}")))
+    .respond_with(
+      ResponseTemplate::new(200).set_body_string(fixture("thread.html", &media_server.uri())),
+    )
+    .mount(&server)
+    .await;
+  Mock::given(method("GET"))
+    .respond_with(ResponseTemplate::new(503))
+    .mount(&media_server)
+    .await;
+  let http_settings = HttpSettings {
+    circuit_failure_threshold: Some(2),
+    circuit_probe_secs: Some(1),
+    ..HttpSettings::default()
+  };
+
+  let scraper = scraper_builder(&server, &data_dir)
+    .client(http_settings.build_client().unwrap())
+    .build()
+    .unwrap();
+
+  scraper.scrape_thread(THREAD_ID).await.unwrap();
+
+  let run_stats = scraper.run_stats();
+  assert_eq!(run_stats.get(Stat::MediaFailed), 0);
+  assert_eq!(run_stats.get(Stat::MediaDeferred), 3);
+  // The first media's robots.txt takes the host down, the rest never reach it until a single probe at the end of the run.
+  assert_eq!(media_server.received_requests().await.unwrap().len(), 3);
+
+  let deferred_media = read_json_lines(data_dir.path().join("deferred_media.jsonl"));
+  let deferred_post_ids: Vec<&str> = deferred_media
+    .iter()
+    .map(|deferred| deferred["post_id"].as_str().unwrap())
+    .collect();
+  assert_eq!(deferred_post_ids, ["70000002", "70000004", "70000005"]);
+
+  media_server.reset().await;
+  mount_media(&media_server, "/media/1709294400001.png", FIRST_MEDIA_BYTES).await;
+  mount_media(
+    &media_server,
+    "/media/1709295000002.webm",
+    SECOND_MEDIA_BYTES,
+  )
+  .await;
+  mount_media(&media_server, "/media/1709295600003.png", FIRST_MEDIA_BYTES).await;
+  let empty_file_list = data_dir.path().join("empty_file_list.txt");
+  fs::write(&empty_file_list, "").unwrap();
+
+  let scraper = scraper_builder(&server, &data_dir)
+    .client(http_settings.build_client().unwrap())
+    .build()
+    .unwrap();
+
+  scraper.download_file_list(&empty_file_list).await.unwrap();
+
+  // The third is a repost of the first, so it's deduplicated rather than downloaded again.
+  assert_eq!(scraper.run_stats().get(Stat::MediaDownloaded), 2);
+  assert_eq!(scraper.run_stats().get(Stat::MediaSkipped), 1);
+  assert_eq!(scraper.run_stats().get(Stat::MediaDeferred), 0);
+  assert!(!data_dir.path().join("deferred_media.jsonl").exists());
+  assert_eq!(
+    fs::read(data_dir.path().join("70000001/70000001-70000004-.webm")).unwrap(),
+    SECOND_MEDIA_BYTES
+  );
+}
+
+#[tokio::test]
+async fn the_run_stops_before_going_over_the_output_limit() {
+  let server = MockServer::start().await;
+  let data_dir = TempDir::new().unwrap();
+  mount_thread(&server).await;
+  // The thread's snapshot is written before any media.
+  let snapshot_dir = TempDir::new().unwrap();
+  let snapshot_bytes = write_snapshot(
+    &snapshot_dir.path().join("thread.html.zst"),
+    &fixture("thread.html", &server.uri()),
+  )
+  .unwrap();
+
+  let scraper = scraper_builder(&server, &data_dir)
+    .disk_settings(DiskSettings {
+      max_output_bytes: Some(snapshot_bytes + FIRST_MEDIA_BYTES.len() as u64 + 1),
+      ..DiskSettings::default()
+    })
+    .build()
+    .unwrap();
+
+  let error = scraper.scrape_thread(THREAD_ID).await.unwrap_err();
+
+  assert!(matches!(error, ScrapeError::DiskLimit(_)));
+  assert!(scraper.context().shutdown.is_stopping());
+  assert!(scraper
+    .context()
+    .shutdown
+    .stop_reason()
+    .unwrap()
+    .contains("output limit"));
+  assert_eq!(scraper.run_stats().get(Stat::MediaDownloaded), 1);
+  assert_eq!(scraper.run_stats().get(Stat::MediaFailed), 0);
+  assert!(data_dir
+    .path()
+    .join("70000001/70000001-70000002-.png")
+    .exists());
+  assert!(!data_dir
+    .path()
+    .join("70000001/70000001-70000004-.webm")
+    .exists());
+}
+
+#[tokio::test]
+async fn unchanged_thread_pages_are_served_from_the_page_cache() {
+  let server = MockServer::start().await;
+  let data_dir = TempDir::new().unwrap();
+  let thread_path = format!("/vt/thread/{THREAD_ID}");
+  Mock::given(method("GET"))
+    .and(path(&thread_path))
+    .and(header("if-none-match", "\"thread-v1\""))
+    .respond_with(ResponseTemplate::new(304))
+    .with_priority(1)
+    .mount(&server)
+    .await;
+  Mock::given(method("GET"))
+    .and(path(&thread_path))
+    .respond_with(
+      ResponseTemplate::new(200)
+        .insert_header("etag", "\"thread-v1\"")
+        .set_body_string(fixture("thread.html", &server.uri())),
+    )
+    .mount(&server)
+    .await;
+  mount_media(&server, "/media/1709294400001.png", FIRST_MEDIA_BYTES).await;
+  mount_media(&server, "/media/1709295000002.webm", SECOND_MEDIA_BYTES).await;
+  mount_media(&server, "/media/1709295600003.png", FIRST_MEDIA_BYTES).await;
+
+  for _ in 0..2 {
+    let scraper = scraper_builder(&server, &data_dir).build().unwrap();
+
+    scraper.scrape_thread(THREAD_ID).await.unwrap();
+  }
+
+  let thread_requests: Vec<bool> = server
+    .received_requests()
+    .await
+    .unwrap()
+    .iter()
+    .filter(|request| request.url.path() == thread_path)
+    .map(|request| request.headers.contains_key("if-none-match"))
+    .collect();
+  assert_eq!(thread_requests, [false, true]);
+
+  // The second run parsed the cached page, finding the media it already downloaded.
+  let media_requests = requested_paths(&server)
+    .await
+    .into_iter()
+    .filter(|requested_path| requested_path.starts_with("/media/"))
+    .count();
+  assert_eq!(media_requests, 2);
+
+  // Only the validators are cached, the page itself being its snapshot.
+  let cache_entries: Vec<Value> = fs::read_dir(data_dir.path().join("page_cache"))
+    .unwrap()
+    .map(|entry| serde_json::from_str(&fs::read_to_string(entry.unwrap().path()).unwrap()).unwrap())
+    .collect();
+  assert_eq!(cache_entries.len(), 1);
+  assert!(cache_entries[0].get("body").is_none());
+
+  // Without the snapshot there's nothing to revalidate.
+  fs::remove_file(
+    data_dir
+      .path()
+      .join(format!("{THREAD_ID}/thread_{THREAD_ID}.html.zst")),
+  )
+  .unwrap();
+  let scraper = scraper_builder(&server, &data_dir).build().unwrap();
+  scraper.scrape_thread(THREAD_ID).await.unwrap();
+
+  let requests = server.received_requests().await.unwrap();
+  let last_thread_request = requests
+    .iter()
+    .rev()
+    .find(|request| request.url.path() == thread_path)
+    .unwrap();
+  assert!(!last_thread_request.headers.contains_key("if-none-match"));
+
+  let uncached_scraper = scraper_builder(&server, &data_dir)
+    .page_cache(false)
+    .build()
+    .unwrap();
+  uncached_scraper.scrape_thread(THREAD_ID).await.unwrap();
+
+  let requests = server.received_requests().await.unwrap();
+  let last_thread_request = requests
+    .iter()
+    .rev()
+    .find(|request| request.url.path() == thread_path)
+    .unwrap();
+  assert!(!last_thread_request.headers.contains_key("if-none-match"));
+}
+
+#[tokio::test]
+async fn replays_extract_links_from_saved_snapshots_without_the_network() {
+  let server = MockServer::start().await;
+  let data_dir = TempDir::new().unwrap();
+  mount_thread(&server).await;
+
+  let scraper = scraper_builder(&server, &data_dir).build().unwrap();
+  scraper.scrape_thread(THREAD_ID).await.unwrap();
+
+  let hyperlinks = fs::read_to_string(data_dir.path().join("urls.txt")).unwrap();
+  fs::remove_file(data_dir.path().join("urls.txt")).unwrap();
+  server.reset().await;
+
+  let replay_scraper = scraper_builder(&server, &data_dir)
+    .replay(true)
+    .build()
+    .unwrap();
+  replay_scraper.replay_snapshots().await.unwrap();
+
+  assert!(server.received_requests().await.unwrap().is_empty());
+  assert_eq!(
+    fs::read_to_string(data_dir.path().join("urls.txt")).unwrap(),
+    hyperlinks
+  );
+
+  let run_stats = replay_scraper.run_stats();
+  assert_eq!(run_stats.get(Stat::ThreadDone), 1);
+  assert_eq!(run_stats.get(Stat::MediaDownloaded), 0);
+  assert_eq!(run_stats.get(Stat::MediaSkipped), 3);
+
+  let error = replay_scraper.scrape_thread("70000099").await.unwrap_err();
+  assert!(matches!(error, ScrapeError::MissingSnapshot { .. }));
+
+  let live_scraper = scraper_builder(&server, &data_dir).build().unwrap();
+  assert!(matches!(
+    live_scraper.replay_snapshots().await,
+    Err(ScrapeError::Config(_))
+  ));
+}
+
+#[tokio::test]
+async fn thread_lists_are_scraped_without_searching() {
+  let server = MockServer::start().await;
+  let data_dir = TempDir::new().unwrap();
+  mount_thread(&server).await;
+  let thread_list = format!("{THREAD_ID}\n{}/vt/thread/{THREAD_ID}/\n", server.uri());
+
+  let scraper = scraper_builder(&server, &data_dir).build().unwrap();
+  scraper
+    .scrape_thread_list(thread_list.as_bytes())
+    .await
+    .unwrap();
+
+  let page_requests: Vec<String> = requested_paths(&server)
+    .await
+    .into_iter()
+    .filter(|requested_path| !requested_path.starts_with("/media/"))
+    .collect();
+  assert_eq!(page_requests, vec![format!("/vt/thread/{THREAD_ID}")]);
+
+  let run_stats = scraper.run_stats();
+  assert_eq!(run_stats.get(Stat::ThreadQueued), 1);
+  assert_eq!(run_stats.get(Stat::ThreadDone), 1);
+  assert_eq!(run_stats.get(Stat::MediaDownloaded), 2);
+}
+
+#[tokio::test]
+async fn thread_lists_stopped_early_return_the_threads_left() {
+  let server = MockServer::start().await;
+  let data_dir = TempDir::new().unwrap();
+  let shutdown = ShutdownSignal::default();
+  Mock::given(method("GET"))
+    .and(path(format!("/vt/thread/{THREAD_ID}")))
+    .respond_with(StopWhileResponding {
+      shutdown: shutdown.clone(),
+      response: ResponseTemplate::new(200).set_body_string(fixture("thread.html", &server.uri())),
+    })
+    .mount(&server)
+    .await;
+  let thread_list = format!("{THREAD_ID}\n69000001\n");
+
+  let scraper = scraper_builder(&server, &data_dir)
+    .shutdown_signal(shutdown)
+    .build()
+    .unwrap();
+  let unprocessed_thread_ids = scraper
+    .scrape_thread_list(thread_list.as_bytes())
+    .await
+    .unwrap();
+
+  // The thread cut short counts as unprocessed along with the one after it.
+  assert_eq!(unprocessed_thread_ids, [THREAD_ID, "69000001"]);
+  assert_eq!(scraper.run_stats().get(Stat::ThreadDone), 0);
+}
+
+#[tokio::test]
+async fn thread_lists_stopped_by_the_archive_going_down_count_the_rest_as_failed() {
+  let server = MockServer::start().await;
+  let data_dir = TempDir::new().unwrap();
+  Mock::given(method("GET"))
+    .and(path_regex("^/vt/thread/"))
+    .respond_with(ResponseTemplate::new(503))
+    .mount(&server)
+    .await;
+  let http_settings = HttpSettings {
+    circuit_failure_threshold: Some(1),
+    circuit_probe_secs: Some(600),
+    ..HttpSettings::default()
+  };
+
+  let scraper = scraper_builder(&server, &data_dir)
+    .client(http_settings.build_client().unwrap())
+    .build()
+    .unwrap();
+  scraper
+    .scrape_thread_ids(&["69000001".into(), "69000002".into(), "69000003".into()])
+    .await;
+
+  let run_stats = scraper.run_stats();
+  assert_eq!(run_stats.get(Stat::ThreadQueued), 3);
+  assert_eq!(run_stats.get(Stat::ThreadDone), 0);
+  assert_eq!(run_stats.get(Stat::ThreadFailed), 3);
+
+  let failed_thread_ids: Vec<Value> = read_json_lines(data_dir.path().join("failed_items.jsonl"))
+    .into_iter()
+    .map(|failed_item| failed_item["thread_id"].clone())
+    .collect();
+  assert_eq!(failed_thread_ids, ["69000001", "69000002", "69000003"]);
+}
+
+#[tokio::test]
+async fn failed_threads_are_kept_until_a_retry_gets_them_through() {
+  let server = MockServer::start().await;
+  let data_dir = TempDir::new().unwrap();
+  mount_thread(&server).await;
+  Mock::given(method("GET"))
+    .and(path("/vt/search/subject/%2Fshon%2F/page/1"))
+    .respond_with(
+      ResponseTemplate::new(200).set_body_string(fixture("search_page.html", &server.uri())),
+    )
+    .mount(&server)
+    .await;
+
+  let scraper = scraper_builder(&server, &data_dir).build().unwrap();
+  scraper.scrape_page_range(1..=1).await;
+
+  let failed_items_path = data_dir.path().join("failed_items.jsonl");
+  let failed_items = read_json_lines(failed_items_path.clone());
+  assert_eq!(failed_items.len(), 1);
+  assert_eq!(failed_items[0]["kind"], "thread");
+  assert_eq!(failed_items[0]["thread_id"], "69000001");
+  assert_eq!(failed_items[0]["error_class"], "client_error");
+
+  // Only other classes are retried, so the thread is left alone.
+  let network_retry = scraper_builder(&server, &data_dir).build().unwrap();
+  network_retry.retry_failed(&[ErrorClass::Network]).await;
+  assert_eq!(network_retry.run_stats().get(Stat::ThreadQueued), 0);
+  assert_eq!(read_json_lines(failed_items_path.clone()).len(), 1);
+
+  Mock::given(method("GET"))
+    .and(path("/vt/thread/69000001"))
+    .respond_with(ResponseTemplate::new(200).set_body_string(fixture("thread.html", &server.uri())))
+    .mount(&server)
+    .await;
+
+  let retry = scraper_builder(&server, &data_dir).build().unwrap();
+  retry.retry_failed(&[ErrorClass::ClientError]).await;
+
+  assert_eq!(retry.run_stats().get(Stat::ThreadDone), 1);
+  assert!(!failed_items_path.exists());
+  assert_eq!(
+    requested_paths(&server)
+      .await
+      .iter()
+      .filter(|requested_path| *requested_path == "/vt/thread/69000001")
+      .count(),
+    2
+  );
+}
+
+/// Serves the response while asking the run to stop, as a shutdown signal arriving mid-request would.
+struct StopWhileResponding {
+  shutdown: ShutdownSignal,
+  response: ResponseTemplate,
+}
+
+impl Respond for StopWhileResponding {
+  fn respond(&self, _: &Request) -> ResponseTemplate {
+    self.shutdown.request_stop("stopped by the test");
+
+    self.response.clone()
+  }
+}
+
+#[tokio::test]
+async fn retries_stopped_partway_keep_what_they_did_not_finish() {
+  let server = MockServer::start().await;
+  let data_dir = TempDir::new().unwrap();
+  let shutdown = ShutdownSignal::default();
+  Mock::given(method("GET"))
+    .and(path("/vt/search/subject/%2Fshon%2F/page/1"))
+    .respond_with(
+      ResponseTemplate::new(200).set_body_string(fixture("search_page.html", &server.uri())),
+    )
+    .mount(&server)
+    .await;
+  Mock::given(method("GET"))
+    .and(path(format!("/vt/thread/{THREAD_ID}")))
+    .respond_with(StopWhileResponding {
+      shutdown: shutdown.clone(),
+      response: ResponseTemplate::new(200).set_body_string(fixture("thread.html", &server.uri())),
+    })
+    .mount(&server)
+    .await;
+  let failed_items_path = data_dir.path().join("failed_items.jsonl");
+  fs::write(
+    &failed_items_path,
+    concat!(
+      r#"{"kind":"page","page_number":1,"error_class":"server_error","reason":"503","failed_at":"2024-03-02T00:00:00Z"}"#,
+      "\n",
+      r#"{"kind":"thread","thread_id":"69500000","error_class":"network","reason":"timed out","failed_at":"2024-03-02T00:00:00Z"}"#,
+      "\n",
+    ),
+  )
+  .unwrap();
+
+  let scraper = scraper_builder(&server, &data_dir)
+    .shutdown_signal(shutdown)
+    .build()
+    .unwrap();
+  scraper.retry_failed(&[]).await;
+
+  assert_eq!(scraper.run_stats().get(Stat::ThreadDone), 0);
+  assert_eq!(scraper.run_stats().get(Stat::PageDone), 0);
+  assert!(!requested_paths(&server)
+    .await
+    .contains(&"/vt/thread/69000001".to_string()));
+
+  // The page is kept as the thread cut short and the one after it, and the thread never reached is kept as it was.
+  let failed_items: Vec<(Value, Value, Value)> = read_json_lines(failed_items_path)
+    .into_iter()
+    .map(|failed_item| {
+      (
+        failed_item["kind"].clone(),
+        failed_item["thread_id"].clone(),
+        failed_item["error_class"].clone(),
+      )
+    })
+    .collect();
+  assert_eq!(
+    failed_items,
+    [
+      ("thread".into(), THREAD_ID.into(), "server_error".into()),
+      ("thread".into(), "69000001".into(), "server_error".into()),
+      ("thread".into(), "69500000".into(), "network".into()),
+    ]
+  );
+}
+
+#[tokio::test]
+async fn media_retries_stopped_by_the_output_limit_are_kept_as_they_were() {
+  let server = MockServer::start().await;
+  let data_dir = TempDir::new().unwrap();
+  mount_media(&server, "/media/1709294400001.png", FIRST_MEDIA_BYTES).await;
+  let failed_items_path = data_dir.path().join("failed_items.jsonl");
+  let failed_media = format!(
+    r#"{{"kind":"media","board":"vt","thread_id":"{THREAD_ID}","post_id":"70000002","timestamp":null,"file_appender":"","media":{{"url":"{}/media/1709294400001.png","extension":"png","original_name":null,"md5":null,"advertised_size":null,"dimensions":null}},"error_class":"server_error","reason":"503","failed_at":"2024-03-02T00:00:00Z"}}"#,
+    server.uri()
+  );
+  fs::write(&failed_items_path, format!("{failed_media}\n")).unwrap();
+
+  let scraper = scraper_builder(&server, &data_dir)
+    .disk_settings(DiskSettings {
+      max_output_bytes: Some(1),
+      ..DiskSettings::default()
+    })
+    .build()
+    .unwrap();
+  scraper.retry_failed(&[]).await;
+
+  assert_eq!(scraper.run_stats().get(Stat::MediaFailed), 0);
+  assert_eq!(
+    read_json_lines(failed_items_path),
+    [serde_json::from_str::<Value>(&failed_media).unwrap()]
+  );
+}
+
+#[tokio::test]
+async fn posts_failing_the_post_filter_are_skipped() {
+  let server = MockServer::start().await;
+  let data_dir = TempDir::new().unwrap();
+  mount_thread(&server).await;
+
+  let scraper = scraper_builder(&server, &data_dir)
+    .post_filter(PostFilter {
+      replies_to: vec![THREAD_ID.to_string()],
+      ..PostFilter::default()
+    })
+    .build()
+    .unwrap();
+  scraper.scrape_thread(THREAD_ID).await.unwrap();
+
+  assert_eq!(
+    requested_paths(&server)
+      .await
+      .into_iter()
+      .filter(|requested_path| requested_path.starts_with("/media/"))
+      .collect::<Vec<_>>(),
+    vec!["/media/1709294400001.png"]
+  );
+  assert_eq!(
+    fs::read_to_string(data_dir.path().join("urls.txt")).unwrap(),
+    "70000001-70000002: https://pomf2.lain.la/f/abc123.png\n"
+  );
+  assert_eq!(
+    scraper.run_stats().summary().filter_rejections["not a reply to a watched post"],
+    4
+  );
+}
+
+#[tokio::test]
+async fn the_storage_layout_comes_from_the_profile() {
+  let server = MockServer::start().await;
+  let data_dir = TempDir::new().unwrap();
+  mount_thread(&server).await;
+  let profile: Profile = toml::from_str(&format!(
+    r#"
+[storage]
+data_dir = {:?}
+media_path_template = "{{board}}/{{md5}}.{{ext}}"
+metadata_path_template = "metadata.jsonl"
+hard_link_duplicate_media = false
+"#,
+    data_dir.path()
+  ))
+  .unwrap();
+
+  let scraper = Scraper::builder()
+    .backend(backend(&server))
+    .storage(profile.storage.clone())
+    .build()
+    .unwrap();
+  scraper.scrape_thread(THREAD_ID).await.unwrap();
+
+  // The thread's snapshot is kept next to its media.
+  let media_files: Vec<PathBuf> = files_in(data_dir.path())
+    .into_iter()
+    .filter(|file| file.starts_with("vt"))
+    .collect();
+  assert_eq!(
+    media_files,
+    vec![
+      PathBuf::from("vt/82f2b7ce46ecb0bc76f7b70e52fbb02f.png"),
+      PathBuf::from("vt/9d5b3dc32e7d34f6de1d9307b408d907.webm"),
+      PathBuf::from("vt/thread_70000001.html.zst"),
+    ]
+  );
+  assert!(data_dir.path().join("metadata.jsonl").exists());
+
+  let replay_scraper = Scraper::builder()
+    .backend(backend(&server))
+    .storage(profile.storage.clone())
+    .replay(true)
+    .build()
+    .unwrap();
+  replay_scraper.replay_snapshots().await.unwrap();
+  assert_eq!(replay_scraper.run_stats().get(Stat::ThreadDone), 1);
+
+  assert_ne!(
+    profile
+      .storage
+      .search_page_snapshot_path("https://archive.example/vt/search/subject/a", 1),
+    profile
+      .storage
+      .search_page_snapshot_path("https://archive.example/vt/search/subject/b", 1)
+  );
+  assert!(toml::from_str::<Profile>("[storage]\nmedia_path_template = \"{unknown}\"").is_err());
+}
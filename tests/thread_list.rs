@@ -0,0 +1,47 @@
+use thread_archive_scraper::thread_list::*;
+
+fn reference(board: Option<&str>, thread_id: &str) -> Option<ThreadReference> {
+  Some(ThreadReference {
+    board: board.map(str::to_string),
+    thread_id: thread_id.to_string(),
+  })
+}
+
+#[test]
+fn bare_ids_archive_urls_and_live_board_urls_are_threads() {
+  assert_eq!(
+    parse_thread_reference(" 70000001 "),
+    reference(None, "70000001")
+  );
+  assert_eq!(
+    parse_thread_reference("https://archive.palanq.win/vt/thread/70000001/#70000005"),
+    reference(Some("vt"), "70000001")
+  );
+  assert_eq!(
+    parse_thread_reference("https://boards.4chan.org/vt/thread/70000001/some-subject"),
+    reference(Some("vt"), "70000001")
+  );
+  assert_eq!(
+    parse_thread_reference("https://archive.palanq.win/vt/search/subject/shon"),
+    None
+  );
+  assert_eq!(parse_thread_reference("thread 70000001"), None);
+}
+
+#[test]
+fn thread_lists_skip_comments_repeats_and_other_boards() {
+  let thread_list = "\
+# From the weekly handoff
+70000001
+https://archive.palanq.win/vt/thread/70000001/
+
+https://boards.4chan.org/g/thread/80000000
+not a thread
+https://boards.4chan.org/vt/thread/70000002/
+";
+
+  assert_eq!(
+    read_thread_list(thread_list.as_bytes(), "vt").unwrap(),
+    vec!["70000001", "70000002"]
+  );
+}